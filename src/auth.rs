@@ -1,9 +1,16 @@
 use anyhow::{anyhow, Result};
 use colored::*;
 use reqwest::Client;
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tokio::time::{sleep, Duration};
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::config::{Config, GitHubAppConfig};
+use crate::credentials::Secret;
+use crate::http;
 
 const CLIENT_ID: &str = "Ov23li7Y8uyN0cW2UHeS";
 
@@ -12,13 +19,34 @@ struct DeviceCodeResponse {
     device_code: String,
     user_code: String,
     verification_uri: String,
+    /// 已填好验证码的链接，GitHub 目前不返回，兼容支持它的服务
+    #[serde(default)]
+    verification_uri_complete: Option<String>,
     expires_in: u64,
     interval: u64,
 }
 
+impl DeviceCodeResponse {
+    /// 浏览器和二维码使用的链接，优先使用已填好验证码的链接
+    fn browser_uri(&self) -> &str {
+        self.verification_uri_complete
+            .as_deref()
+            .unwrap_or(&self.verification_uri)
+    }
+}
+
+/// `auth login` 的选项
+#[derive(Debug, Default)]
+pub struct LoginOptions {
+    /// 优先于配置中的 oauth_client_id（及 DOCKER_SYNC_CLIENT_ID）
+    pub client_id: Option<String>,
+    pub no_browser: bool,
+    pub no_qr: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct AccessTokenResponse {
-    access_token: Option<String>,
+    access_token: Option<Secret>,
     #[allow(dead_code)]
     token_type: Option<String>,
     #[allow(dead_code)]
@@ -29,50 +57,28 @@ struct AccessTokenResponse {
 
 pub struct GitHubAuth {
     client: Client,
+    client_id: String,
 }
 
 impl GitHubAuth {
-    #[allow(dead_code)]
-    pub fn new() -> Self {
-        Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .unwrap_or_else(|_| Client::new()),
-        }
-    }
-    
-    pub fn new_with_proxy(proxy: Option<&str>) -> Self {
-        let mut builder = Client::builder().timeout(Duration::from_secs(30));
-        
-        if let Some(proxy_url) = proxy {
-            match reqwest::Proxy::all(proxy_url) {
-                Ok(proxy) => {
-                    println!("{} 使用代理: {}", "🌐".blue(), proxy_url.cyan());
-                    builder = builder.proxy(proxy);
-                }
-                Err(e) => {
-                    println!("{} 代理配置错误: {} - {}", "⚠️".yellow(), proxy_url, e);
-                    println!("{} 将使用直连方式", "📡".yellow());
-                }
-            }
-        }
-        
-        Self {
-            client: match builder.build() {
-                Ok(client) => client,
-                Err(e) => {
-                    println!("{} 客户端创建失败: {}", "❌".red(), e);
-                    Client::new()
-                }
-            },
+    pub fn new(config: &Config) -> Result<Self> {
+        if let Some(proxy) = http::proxy_for(config, "https://github.com") {
+            println!("{} 使用代理: {}", "🌐".blue(), proxy.as_str().cyan());
         }
+        Ok(Self {
+            client: http::client(config)?,
+            client_id: CLIENT_ID.to_string(),
+        })
     }
 
-    pub async fn login_with_browser() -> Result<String> {
-        use crate::config::Config;
-        let config = Config::load().await.unwrap_or_default();
-        let auth = Self::new_with_proxy(config.proxy.as_deref());
+    pub async fn login_with_browser(options: &LoginOptions) -> Result<Secret> {
+        let config = Config::load().await?;
+        let mut auth = Self::new(&config)?;
+        // 自建的 OAuth App 需启用 Device Flow；DOCKER_SYNC_CLIENT_ID 通过配置层覆盖 oauth_client_id
+        if let Some(client_id) = options.client_id.clone().or(config.oauth_client_id) {
+            println!("{} 使用 OAuth App: {}", "🔑".blue(), client_id.cyan());
+            auth.client_id = client_id.trim().to_string();
+        }
 
         println!("{}", "正在连接 GitHub...".blue());
 
@@ -84,7 +90,7 @@ impl GitHubAuth {
         println!("{}", "📋 请完成以下步骤:".yellow());
         println!(
             "1. 在浏览器中打开: {}",
-            device_code_response.verification_uri.cyan()
+            device_code_response.browser_uri().cyan()
         );
         println!(
             "2. 输入验证码: {}",
@@ -93,26 +99,21 @@ impl GitHubAuth {
         println!("3. 授权应用");
         println!();
 
-        // 尝试打开浏览器（仅在桌面环境）
-        #[cfg(windows)]
-        {
-            let _ = webbrowser::open(&device_code_response.verification_uri);
-        }
-
-        #[cfg(target_os = "macos")]
-        {
-            let _ = std::process::Command::new("open")
-                .arg(&device_code_response.verification_uri)
-                .spawn();
+        // 跳板机等无浏览器环境，可用手机扫码打开
+        if !options.no_qr {
+            match render_qr_code(device_code_response.browser_uri()) {
+                Ok(qr_code) => {
+                    println!("{}", "📱 或用手机扫描二维码:".yellow());
+                    println!("{}", qr_code);
+                }
+                Err(e) => println!("{} 无法生成二维码: {}", "⚠️".yellow(), e),
+            }
         }
 
-        #[cfg(target_os = "linux")]
-        {
-            // Linux 服务器环境不自动打开浏览器，用户手动在本地浏览器打开
+        if !options.no_browser && open_browser(device_code_response.browser_uri()) {
+            println!("{}", "🌐 已尝试在浏览器中打开".blue());
         }
 
-        println!("{}", "⏳ 等待授权...".blue());
-
         // 3. 轮询获取访问令牌
         let token = auth.poll_for_token(&device_code_response).await?;
 
@@ -121,7 +122,7 @@ impl GitHubAuth {
 
     async fn get_device_code(&self) -> Result<DeviceCodeResponse> {
         let mut params = HashMap::new();
-        params.insert("client_id", CLIENT_ID);
+        params.insert("client_id", self.client_id.as_str());
         params.insert(
             "scope",
             "repo workflow write:packages read:packages delete:packages",
@@ -131,7 +132,6 @@ impl GitHubAuth {
             .client
             .post("https://github.com/login/device/code")
             .header("Accept", "application/json")
-            .header("User-Agent", "docker-sync-cli")
             .form(&params)
             .send()
             .await?;
@@ -145,69 +145,328 @@ impl GitHubAuth {
         Ok(device_code)
     }
 
-    async fn poll_for_token(&self, device_code: &DeviceCodeResponse) -> Result<String> {
+    async fn poll_for_token(&self, device_code: &DeviceCodeResponse) -> Result<Secret> {
         let mut interval = device_code.interval;
-        let max_attempts = device_code.expires_in / interval;
-        let mut attempts = 0;
+        let deadline = Instant::now() + Duration::from_secs(device_code.expires_in);
+
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.blue} {msg}")
+                .unwrap(),
+        );
+        pb.enable_steady_tick(Duration::from_millis(100));
 
-        loop {
-            attempts += 1;
-            if attempts > max_attempts {
-                return Err(anyhow!("Authentication timeout. Please try again."));
+        let result = loop {
+            // 按秒等待，期间刷新验证码剩余有效期
+            for _ in 0..interval {
+                let remaining = deadline.saturating_duration_since(Instant::now()).as_secs();
+                pb.set_message(format!(
+                    "⏳ 等待授权... 验证码 {:02}:{:02} 后过期",
+                    remaining / 60,
+                    remaining % 60
+                ));
+                sleep(Duration::from_secs(1)).await;
+            }
+            if Instant::now() >= deadline {
+                break Err(anyhow!("Authentication timeout. Please try again."));
             }
 
-            sleep(Duration::from_secs(interval)).await;
-
-            let mut params = HashMap::new();
-            params.insert("client_id", CLIENT_ID);
-            params.insert("device_code", device_code.device_code.as_str());
-            params.insert("grant_type", "urn:ietf:params:oauth:grant-type:device_code");
-
-            let response = self
-                .client
-                .post("https://github.com/login/oauth/access_token")
-                .header("Accept", "application/json")
-                .header("User-Agent", "docker-sync-cli")
-                .form(&params)
-                .send()
-                .await?;
-
-            if response.status().is_success() {
-                let token_response: AccessTokenResponse = response.json().await?;
-
-                if let Some(token) = token_response.access_token {
-                    return Ok(token);
-                } else if let Some(error) = token_response.error {
-                    match error.as_str() {
-                        "authorization_pending" => {
-                            // 继续等待
-                            print!(".");
-                            let _ = std::io::Write::flush(&mut std::io::stdout());
-                        }
-                        "slow_down" => {
-                            // 减慢轮询速度
-                            interval += 5;
-                        }
-                        "expired_token" => {
-                            return Err(anyhow!("Device code expired. Please try again."));
-                        }
-                        "access_denied" => {
-                            return Err(anyhow!("Access denied by user."));
-                        }
-                        _ => {
-                            let desc = token_response.error_description.unwrap_or_default();
-                            return Err(anyhow!("Authentication error: {} - {}", error, desc));
-                        }
-                    }
+            match self.request_token(device_code).await {
+                Ok(PollResult::Token(token)) => break Ok(token),
+                Ok(PollResult::Pending) => {}
+                Ok(PollResult::SlowDown) => {
+                    // 减慢轮询速度
+                    interval += 5;
                 }
+                Err(e) => break Err(e),
+            }
+        };
+
+        pb.finish_and_clear();
+        result
+    }
+
+    async fn request_token(&self, device_code: &DeviceCodeResponse) -> Result<PollResult> {
+        let mut params = HashMap::new();
+        params.insert("client_id", self.client_id.as_str());
+        params.insert("device_code", device_code.device_code.as_str());
+        params.insert("grant_type", "urn:ietf:params:oauth:grant-type:device_code");
+
+        let response = self
+            .client
+            .post("https://github.com/login/oauth/access_token")
+            .header("Accept", "application/json")
+            .form(&params)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Ok(PollResult::Pending);
+        }
+
+        let token_response: AccessTokenResponse = response.json().await?;
+        if let Some(token) = token_response.access_token {
+            return Ok(PollResult::Token(token));
+        }
+        match token_response.error.as_deref() {
+            None | Some("authorization_pending") => Ok(PollResult::Pending),
+            Some("slow_down") => Ok(PollResult::SlowDown),
+            Some("expired_token") => Err(anyhow!("Device code expired. Please try again.")),
+            Some("access_denied") => Err(anyhow!("Access denied by user.")),
+            Some(error) => {
+                let desc = token_response.error_description.unwrap_or_default();
+                Err(anyhow!("Authentication error: {} - {}", error, desc))
             }
         }
     }
 }
 
+enum PollResult {
+    Token(Secret),
+    Pending,
+    SlowDown,
+}
+
+/// 以半高字符渲染二维码，适合在终端中显示
+fn render_qr_code(data: &str) -> Result<String> {
+    use qrcode::render::unicode::Dense1x2;
+
+    let code = qrcode::QrCode::new(data.as_bytes())?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// 在桌面会话中用默认浏览器打开链接，SSH 或无图形界面时返回 false
+fn open_browser(url: &str) -> bool {
+    let remote = std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some();
+
+    #[cfg(windows)]
+    {
+        let _ = remote;
+        webbrowser::open(url).is_ok()
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        !remote && std::process::Command::new("open").arg(url).spawn().is_ok()
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let desktop = std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+        !remote
+            && desktop
+            && std::process::Command::new("xdg-open")
+                .arg(url)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .is_ok()
+    }
+}
+
+/// 安装令牌在过期前多久刷新
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 300;
+
+#[derive(Serialize)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+    account: InstallationAccount,
+}
+
+#[derive(Deserialize)]
+struct InstallationAccount {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct InstallationToken {
+    token: Secret,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct AppState {
+    /// (安装 ID, 安装所在账号)
+    installation: Option<(u64, String)>,
+    token: Option<(Secret, DateTime<Utc>)>,
+}
+
+/// 以 GitHub App 身份认证：用私钥签发 JWT，换取安装令牌，过期前自动刷新
+pub struct GitHubApp {
+    app_id: u64,
+    installation_id: Option<u64>,
+    key: EncodingKey,
+    state: tokio::sync::Mutex<AppState>,
+}
+
+impl GitHubApp {
+    /// 从配置读取 GitHub App，环境变量 DOCKER_SYNC_APP_ID / DOCKER_SYNC_APP_INSTALLATION_ID /
+    /// DOCKER_SYNC_APP_PRIVATE_KEY（PEM 内容）优先，便于在 CI 中使用；未配置时返回 None
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let env_app_id = std::env::var("DOCKER_SYNC_APP_ID").ok();
+        let app_config = match (&env_app_id, &config.github_app) {
+            (Some(app_id), _) => GitHubAppConfig {
+                app_id: app_id
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid DOCKER_SYNC_APP_ID: {}", app_id))?,
+                installation_id: None,
+                private_key_path: None,
+            },
+            (None, Some(app_config)) => app_config.clone(),
+            (None, None) => return Ok(None),
+        };
+
+        let installation_id = match std::env::var("DOCKER_SYNC_APP_INSTALLATION_ID") {
+            Ok(id) => Some(
+                id.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid DOCKER_SYNC_APP_INSTALLATION_ID: {}", id))?,
+            ),
+            Err(_) => app_config.installation_id,
+        };
+
+        let pem = match (std::env::var("DOCKER_SYNC_APP_PRIVATE_KEY"), &app_config.private_key_path) {
+            (Ok(pem), _) => pem,
+            (Err(_), Some(path)) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read GitHub App private key {}: {}", path, e))?,
+            (Err(_), None) => {
+                return Err(anyhow!(
+                    "GitHub App private key not configured (set DOCKER_SYNC_APP_PRIVATE_KEY or run 'docker-sync auth app')"
+                ))
+            }
+        };
+
+        Self::new(app_config.app_id, installation_id, &pem).map(Some)
+    }
+
+    pub fn new(app_id: u64, installation_id: Option<u64>, private_key_pem: &str) -> Result<Self> {
+        let key = EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
+            .map_err(|e| anyhow!("Invalid GitHub App private key: {}", e))?;
+        Ok(Self {
+            app_id,
+            installation_id,
+            key,
+            state: tokio::sync::Mutex::new(AppState::default()),
+        })
+    }
+
+    pub fn app_id(&self) -> u64 {
+        self.app_id
+    }
+
+    /// 签发 App JWT，iat 提前 60 秒以容忍时钟偏差，有效期不超过 GitHub 允许的 10 分钟
+    fn jwt(&self) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let claims = AppClaims {
+            iat: now - 60,
+            exp: now + 540,
+            iss: self.app_id.to_string(),
+        };
+        encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .map_err(|e| anyhow!("Failed to sign GitHub App JWT: {}", e))
+    }
+
+    /// 安装 ID 和安装所在账号；未配置安装 ID 时，App 只能有一个安装
+    pub async fn installation(&self, client: &Client) -> Result<(u64, String)> {
+        let mut state = self.state.lock().await;
+        if let Some(installation) = &state.installation {
+            return Ok(installation.clone());
+        }
+
+        let url = match self.installation_id {
+            Some(id) => format!("https://api.github.com/app/installations/{}", id),
+            None => "https://api.github.com/app/installations".to_string(),
+        };
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.jwt()?))
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to get GitHub App installation: {} {}", status, error_text));
+        }
+
+        let installation = if self.installation_id.is_some() {
+            response.json::<Installation>().await?
+        } else {
+            let mut installations: Vec<Installation> = response.json().await?;
+            match installations.len() {
+                1 => installations.remove(0),
+                0 => return Err(anyhow!("GitHub App {} is not installed on any account", self.app_id)),
+                _ => {
+                    let accounts: Vec<String> = installations
+                        .iter()
+                        .map(|installation| format!("{} ({})", installation.account.login, installation.id))
+                        .collect();
+                    return Err(anyhow!(
+                        "GitHub App {} has several installations, choose one with --installation-id: {}",
+                        self.app_id,
+                        accounts.join(", ")
+                    ));
+                }
+            }
+        };
+
+        let installation = (installation.id, installation.account.login);
+        state.installation = Some(installation.clone());
+        Ok(installation)
+    }
+
+    /// 获取安装令牌，缓存到过期前 5 分钟
+    pub async fn installation_token(&self, client: &Client) -> Result<Secret> {
+        let (installation_id, _) = self.installation(client).await?;
+
+        let mut state = self.state.lock().await;
+        if let Some((token, expires_at)) = &state.token {
+            if *expires_at - Utc::now() > chrono::Duration::seconds(TOKEN_REFRESH_MARGIN_SECS) {
+                return Ok(token.clone());
+            }
+        }
+
+        let response = client
+            .post(format!(
+                "https://api.github.com/app/installations/{}/access_tokens",
+                installation_id
+            ))
+            .header("Authorization", format!("Bearer {}", self.jwt()?))
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to create installation token: {} {}", status, error_text));
+        }
+
+        let token: InstallationToken = response.json().await?;
+        state.token = Some((token.token.clone(), token.expires_at));
+        Ok(token.token)
+    }
+}
+
 // 备用：手动创建token页面
 pub fn open_github_token_page() -> Result<()> {
-    let token_url = "https://github.com/settings/tokens/new?description=docker-sync-cli&scopes=repo,workflow,write:packages";
+    let token_url = "https://github.com/settings/tokens/new?description=docker-sync-cli&scopes=repo,workflow,write:packages,read:packages,delete:packages";
 
     println!(
         "{}",
@@ -219,16 +478,10 @@ pub fn open_github_token_page() -> Result<()> {
     println!("2. The description and scopes are pre-filled");
     println!("3. Click 'Generate token'");
     println!("4. Copy the generated token");
-    println!("5. Run: docker-sync auth token YOUR_COPIED_TOKEN");
+    println!("5. Run: docker-sync auth token (paste the token when prompted)");
     println!();
 
-    #[cfg(windows)]
-    {
-        let _ = webbrowser::open(token_url);
-    }
-
-    #[cfg(not(windows))]
-    {
+    if !open_browser(token_url) {
         println!("Please visit: {}", token_url.cyan());
     }
 
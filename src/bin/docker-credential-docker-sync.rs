@@ -0,0 +1,132 @@
+//! Docker 凭据助手：为 ghcr_registry（默认 ghcr.io）和 helper_registries 中的镜像源提供 docker-sync 保存的 GitHub 令牌
+//!
+//! 协议见 https://github.com/docker/docker-credential-helpers ，
+//! 由 `docker-sync auth setup-docker` 写入 ~/.docker/config.json 的 credHelpers。
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use docker_sync::config::{registry_host, Config};
+use docker_sync::credentials::Secret;
+
+/// docker 约定的“未找到凭据”输出，docker 据此回退到匿名拉取
+const NOT_FOUND: &str = "credentials not found in native keychain";
+
+/// 设为 1 时允许 docker login 把凭据保存为 docker-sync 的令牌
+const STORE_ENV: &str = "DOCKER_SYNC_HELPER_STORE";
+
+/// GHCR 只校验令牌，用户名可以任意填写
+const DEFAULT_USERNAME: &str = "docker-sync";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Credentials {
+    #[serde(rename = "ServerURL")]
+    server_url: String,
+    username: String,
+    secret: String,
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        // 协议要求错误信息输出到 stdout
+        println!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    // profile 由 DOCKER_SYNC_PROFILE 环境变量选择，与外部凭据助手命令使用的相同
+    let action = std::env::args().nth(1).unwrap_or_default();
+
+    match action.as_str() {
+        "get" => {
+            let server_url = read_stdin()?;
+            let config = Config::load_with_token().await?;
+            let token = match config.token() {
+                Some(token) if is_served(&config, &server_url) => token.to_string(),
+                _ => return Err(anyhow!(NOT_FOUND)),
+            };
+            let credentials = Credentials {
+                server_url,
+                username: username(&config),
+                secret: token,
+            };
+            println!("{}", serde_json::to_string(&credentials)?);
+            Ok(())
+        }
+        "store" => {
+            let credentials: Credentials = serde_json::from_str(&read_stdin()?)?;
+            // docker login 使用的令牌可能只有 read:packages，默认不覆盖同步使用的令牌
+            if std::env::var(STORE_ENV).as_deref() != Ok("1") {
+                return Err(anyhow!(
+                    "docker-sync does not replace its GitHub token on docker login; \
+                     run 'docker-sync auth token' instead, or set {}=1 to allow it",
+                    STORE_ENV
+                ));
+            }
+            let mut config = Config::load().await?;
+            if !is_served(&config, &credentials.server_url) {
+                return Err(anyhow!(
+                    "{} is not a docker-sync registry (served: {})",
+                    credentials.server_url,
+                    config.credential_helper_hosts().join(", ")
+                ));
+            }
+            // docker login ghcr.io 时保存的就是 GitHub 令牌
+            config.set_token(Some(Secret::new(credentials.secret))).await?;
+            config.save().await
+        }
+        "erase" => {
+            // 令牌同时用于同步，docker logout 时保留，需用 docker-sync auth logout 清除
+            let server_url = read_stdin()?;
+            let config = Config::load().await?;
+            if is_served(&config, &server_url) {
+                eprintln!(
+                    "The GitHub token is kept for docker-sync; run 'docker-sync auth logout' to remove it"
+                );
+            }
+            Ok(())
+        }
+        "list" => {
+            let config = Config::load_with_token().await?;
+            let mut servers = BTreeMap::new();
+            if config.token().is_some() {
+                let username = username(&config);
+                for host in config.credential_helper_hosts() {
+                    servers.insert(host, username.clone());
+                }
+            }
+            println!("{}", serde_json::to_string(&servers)?);
+            Ok(())
+        }
+        "version" => {
+            println!("docker-credential-docker-sync {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "Usage: docker-credential-docker-sync <get|store|erase|list|version>"
+        )),
+    }
+}
+
+fn read_stdin() -> Result<String> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+fn username(config: &Config) -> String {
+    config
+        .owner
+        .clone()
+        .unwrap_or_else(|| DEFAULT_USERNAME.to_string())
+}
+
+/// 是否为 ghcr_registry 或 helper_registries 中的主机
+fn is_served(config: &Config, server_url: &str) -> bool {
+    config.credential_helper_hosts().contains(&registry_host(server_url))
+}
@@ -1,58 +1,473 @@
 use anyhow::Result;
+use colored::*;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 use tokio::fs;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::credentials::{write_private_file, CredentialStore, Secret};
+use crate::settings::Origin;
+
+/// 命令行 --token 参数，优先级最高
+static TOKEN_FLAG: OnceLock<Secret> = OnceLock::new();
+
+/// 环境变量按顺序查找
+const TOKEN_ENV_VARS: [&str; 2] = ["DOCKER_SYNC_TOKEN", "GITHUB_TOKEN"];
+
+/// GitHub 令牌的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    Flag,
+    Env(&'static str),
+    GhCli,
+    Stored,
+}
+
+impl TokenSource {
+    pub fn describe(&self) -> String {
+        match self {
+            TokenSource::Flag => "--token flag".to_string(),
+            TokenSource::Env(name) => format!("{} environment variable", name),
+            TokenSource::GhCli => "gh auth token".to_string(),
+            TokenSource::Stored => "stored credentials".to_string(),
+        }
+    }
+}
+
+/// 命令行 --profile 参数，优先于 current_profile
+static PROFILE_FLAG: OnceLock<String> = OnceLock::new();
+
+/// 未指定 --profile 时选择 profile 的环境变量，优先于 current_profile
+const PROFILE_ENV: &str = "DOCKER_SYNC_PROFILE";
+
+/// 顶层配置对应的 profile 名称
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 是否允许选择尚不存在的 profile（保存配置时创建）
+static ALLOW_NEW_PROFILE: AtomicBool = AtomicBool::new(false);
+
+/// 记录命令行选择的 profile，之后所有 Config::load 都会使用它
+pub fn set_profile_flag(profile: &str) {
+    let _ = PROFILE_FLAG.set(profile.trim().to_string());
+}
+
+/// 创建 profile 的命令（auth login、auth token、config profile）调用，允许选择不存在的 profile
+pub fn allow_new_profile() {
+    ALLOW_NEW_PROFILE.store(true, Ordering::Relaxed);
+}
+
+/// profile 名称用于令牌文件名，只允许字母、数字、- 和 _
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "无效的 profile 名称 '{}'，只能包含字母、数字、- 和 _",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// 记录命令行传入的令牌，之后所有 Config::load 都会优先使用
+pub fn set_token_flag(token: &str) {
+    let _ = TOKEN_FLAG.set(Secret::new(token.trim()));
+}
+
+/// 当前配置文件格式版本，格式变化时递增并在 migrate 中补充迁移步骤
+const CONFIG_VERSION: u32 = 2;
+
+/// 缺失的字段使用默认值，旧版本的配置在加载时迁移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
-    pub github_token: Option<String>,
+    /// 配置文件格式版本，旧版本文件没有此字段（视为 1）
+    pub version: u32,
+    /// 按 --token、环境变量、gh CLI、credential_store 的顺序解析，不写入 config.json
+    /// （旧版本明文保存的令牌会在加载时迁移）
+    #[serde(skip_serializing)]
+    pub github_token: Option<Secret>,
+    #[serde(skip)]
+    pub token_source: Option<TokenSource>,
+    /// 令牌是否已解析；解析可能需要输入口令或运行 gh，只在命令需要令牌时进行
+    #[serde(skip)]
+    token_resolved: bool,
+    /// 旧版本 config.json 中明文保存的令牌
+    #[serde(skip)]
+    legacy_token: Option<Secret>,
+    /// GitHub 令牌的存储方式
+    pub credential_store: CredentialStore,
+    /// 以 GitHub App 身份认证（CI 中使用），配置后优先于个人令牌
+    pub github_app: Option<GitHubAppConfig>,
+    /// 同步的目标仓库，镜像推送到 ghcr.io/<owner>/
     pub ghcr_registry: String,
-    pub nju_registry: String,
-    pub default_registry: String,
+    /// 拉取镜像使用的镜像源（如 ghcr.nju.edu.cn 或 ghcr.io），替代旧版本的 nju_registry / default_registry
+    pub pull_registry: String,
     pub custom_registries: Vec<String>,
     pub proxy: Option<String>,
+    /// 按主机选择代理，值为代理地址或 direct，优先于 proxy 和 NO_PROXY
+    pub proxy_rules: BTreeMap<String, String>,
+    /// 建立连接的超时时间（秒）
+    pub connect_timeout: u64,
+    /// 单个请求（包括读取响应）的超时时间（秒）
+    pub request_timeout: u64,
+    /// 额外信任的 CA 证书文件（PEM），用于企业网络的 TLS 代理
+    pub ca_bundles: Vec<String>,
+    /// docker 凭据助手除 ghcr_registry 外还提供 GitHub 令牌的镜像源（需确认其由可信方运营）
+    pub helper_registries: Vec<String>,
+    /// 同步到的 GitHub 账号或组织，为空时使用令牌所属用户
+    pub owner: Option<String>,
+    /// 同步仓库名称，为空时使用 docker-sync
+    pub repo: Option<String>,
+    /// 设备授权登录使用的 OAuth App client ID，为空时使用内置的 App
+    pub oauth_client_id: Option<String>,
+    /// 同步工作流的 runs-on 标签（自托管或更大规格的 Runner），为空时使用 ubuntu-latest
+    pub runs_on: Vec<String>,
+    /// 命名 profile，各自使用独立的令牌、目标账号、镜像源和代理
+    pub profiles: BTreeMap<String, Profile>,
+    /// `config use` 选择的 profile，为空时使用顶层配置
+    pub current_profile: Option<String>,
+    /// 本次运行生效的 profile（--profile 或 current_profile）
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+    /// 应用 profile 前的顶层配置，保存时写回
+    #[serde(skip)]
+    top_level: Option<Profile>,
+    /// 项目配置 (.docker-sync.toml) 中列出的镜像，pull/watch 未指定镜像时使用
+    #[serde(skip)]
+    pub project_images: Vec<String>,
+    /// 每个配置项的来源
+    #[serde(skip)]
+    pub(crate) origins: BTreeMap<&'static str, Origin>,
+    /// 系统、项目和环境变量叠加的配置项及叠加后的值
+    #[serde(skip)]
+    pub(crate) layered: BTreeMap<&'static str, Option<String>>,
+    /// 叠加前（用户配置及 profile）的配置
+    #[serde(skip)]
+    pub(crate) base: Option<Box<Config>>,
+}
+
+/// 命名 profile 的配置项，未设置的项使用内置默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    /// 同步到的 GitHub 账号或组织，为空时使用令牌所属用户
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// 同步仓库名称，为空时使用 docker-sync
+    #[serde(default)]
+    pub repo: Option<String>,
+    /// 拉取镜像使用的镜像源
+    #[serde(default)]
+    pub registry: Option<String>,
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubAppConfig {
+    pub app_id: u64,
+    /// 未设置时使用 App 唯一的安装
+    #[serde(default)]
+    pub installation_id: Option<u64>,
+    pub private_key_path: Option<String>,
 }
 
 impl Config {
     pub async fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
         
-        if !config_path.exists() {
-            return Ok(Self::default());
+        let mut config = if config_path.exists() {
+            let content = fs::read_to_string(&config_path).await?;
+            let (config, migrated) = Self::parse(&content)
+                .map_err(|e| anyhow::anyhow!("配置文件 {}: {}", config_path.display(), e))?;
+            if let Some(from) = migrated {
+                // 迁移前先备份原文件，迁移后的配置立即写回
+                let backup = backup_path(&config_path, from);
+                write_private_file(&backup, backup_content(&content)?.as_bytes()).await?;
+                config.save().await?;
+                eprintln!(
+                    "{} 配置文件已从版本 {} 升级到 {}，原文件备份为 {}",
+                    "🔄".green(),
+                    from,
+                    CONFIG_VERSION,
+                    backup.display()
+                );
+            }
+            config
+        } else {
+            Self::default()
+        };
+        
+        // 加载过程中的提示输出到 stderr，docker-credential-docker-sync 的 stdout 只能输出协议内容
+        let profile = PROFILE_FLAG
+            .get()
+            .cloned()
+            .or_else(|| std::env::var(PROFILE_ENV).ok().filter(|profile| !profile.trim().is_empty()))
+            .or_else(|| config.current_profile.clone());
+        if let Some(profile) = profile.filter(|profile| profile != DEFAULT_PROFILE) {
+            validate_profile_name(&profile)?;
+            if !config.profiles.contains_key(&profile) {
+                // 拼错的 profile 不能静默使用默认的目标账号和镜像源
+                if !ALLOW_NEW_PROFILE.load(Ordering::Relaxed) {
+                    let mut names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+                    names.insert(0, DEFAULT_PROFILE);
+                    return Err(anyhow::anyhow!(
+                        "profile '{}' 不存在（可用: {}），运行 'docker-sync config profile {}' 创建",
+                        profile,
+                        names.join(", "),
+                        profile
+                    ));
+                }
+                eprintln!(
+                    "{} profile '{}' 不存在，保存配置时将自动创建",
+                    "⚠️".yellow(),
+                    profile
+                );
+            }
+            config.activate_profile(&profile);
+        }
+        config.apply_layers(&config_path);
+        
+        if let Some(token) = config.github_token.clone() {
+            // 旧版本明文保存在 config.json 中的令牌，迁移到凭据存储（只可能属于默认 profile）
+            match config.credential_store.store(DEFAULT_PROFILE, &token).await {
+                Ok(()) => {
+                    config.save().await?;
+                    eprintln!(
+                        "{} GitHub 令牌已从 config.json 迁移到 {}",
+                        "🔒".green(),
+                        config.credential_store.describe(DEFAULT_PROFILE)
+                    );
+                }
+                Err(e) => eprintln!("{} GitHub 令牌迁移失败: {}", "⚠️".yellow(), e),
+            }
         }
         
-        let content = fs::read_to_string(&config_path).await?;
-        let config: Config = serde_json::from_str(&content)?;
+        config.legacy_token = config.github_token.take();
         Ok(config)
     }
     
+    /// 加载配置并解析 GitHub 令牌，供需要访问 GitHub 的命令使用
+    pub async fn load_with_token() -> Result<Self> {
+        let mut config = Self::load().await?;
+        config.load_token().await;
+        Ok(config)
+    }
+    
+    /// 解析 GitHub 令牌（只进行一次）；Config::load 不会读取令牌，避免每个命令都询问口令
+    pub async fn load_token(&mut self) {
+        if !self.token_resolved {
+            self.resolve_token().await;
+            self.token_resolved = true;
+        }
+    }
+    
+    /// 解析 config.json 的内容，旧版本格式在内存中升级，返回配置和原版本号（无需升级时为 None）
+    pub fn parse(content: &str) -> Result<(Self, Option<u32>)> {
+        let mut value: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| anyhow::anyhow!("不是有效的 JSON: {}", e))?;
+        let migrated = migrate(&mut value)?;
+        let mut config: Config =
+            serde_json::from_value(value).map_err(|e| anyhow::anyhow!("格式错误: {}", e))?;
+        config.version = CONFIG_VERSION;
+        Ok((config, migrated))
+    }
+    
+    /// 依次尝试 --token、DOCKER_SYNC_TOKEN/GITHUB_TOKEN、gh auth token、已保存的令牌
+    async fn resolve_token(&mut self) {
+        let legacy = self.legacy_token.take();
+        
+        let (token, source) = if let Some(token) = TOKEN_FLAG.get() {
+            (Some(token.clone()), TokenSource::Flag)
+        } else if let Some((name, token)) = TOKEN_ENV_VARS.iter().find_map(|name| {
+            std::env::var(name)
+                .ok()
+                .filter(|token| !token.trim().is_empty())
+                .map(|token| (*name, token))
+        }) {
+            (Some(Secret::new(token.trim())), TokenSource::Env(name))
+        } else if let Some(token) = match self.active_profile {
+            // gh CLI 的账号只对应默认 profile
+            Some(_) => None,
+            None => gh_cli_token().await,
+        } {
+            (Some(token), TokenSource::GhCli)
+        } else if legacy.is_some() && self.active_profile.is_none() {
+            (legacy, TokenSource::Stored)
+        } else {
+            // 令牌读取失败（如口令错误）时按未登录处理，避免调用方用默认配置覆盖 config.json
+            let token = match self.credential_store.load(self.profile_name()).await {
+                Ok(token) => token,
+                Err(e) => {
+                    eprintln!("{} 无法读取 GitHub 令牌: {}", "⚠️".yellow(), e);
+                    None
+                }
+            };
+            (token, TokenSource::Stored)
+        };
+        
+        self.token_source = token.as_ref().map(|_| source);
+        self.github_token = token;
+    }
+    
+    /// 是否使用 GitHub App：App 配置对所有 profile 生效，只在没有令牌或令牌来自 gh CLI 时使用，
+    /// --token、环境变量和当前 profile 保存的令牌优先
+    pub fn prefers_github_app(&self) -> bool {
+        matches!(self.token_source, None | Some(TokenSource::GhCli))
+    }
+    
     pub async fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
         
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).await?;
+        // 只写入用户配置；生效的 profile 写回 profiles，顶层恢复为应用 profile 前的值
+        let mut config = self.user_layer();
+        if let (Some(profile), Some(top_level)) = (&self.active_profile, &self.top_level) {
+            config.profiles.insert(profile.clone(), config.profile_fields());
+            config.apply_profile_fields(top_level);
         }
         
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&config_path, content).await?;
+        // 代理地址可能包含密码，同样只允许当前用户读写
+        let content = serde_json::to_string_pretty(&config)?;
+        write_private_file(&config_path, content.as_bytes()).await?;
+        Ok(())
+    }
+    
+    /// 生效的 profile 名称，未选择时为 default
+    pub fn profile_name(&self) -> &str {
+        self.active_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+    
+    /// 用命名 profile 的配置项覆盖顶层配置
+    fn activate_profile(&mut self, profile: &str) {
+        let fields = self.profiles.get(profile).cloned().unwrap_or_default();
+        self.top_level = Some(self.profile_fields());
+        self.apply_profile_fields(&fields);
+        self.active_profile = Some(profile.to_string());
+    }
+    
+    fn profile_fields(&self) -> Profile {
+        Profile {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            registry: Some(self.pull_registry.clone()),
+            proxy: self.proxy.clone(),
+        }
+    }
+    
+    fn apply_profile_fields(&mut self, profile: &Profile) {
+        self.owner = profile.owner.clone();
+        self.repo = profile.repo.clone();
+        self.pull_registry = profile
+            .registry
+            .clone()
+            .unwrap_or_else(|| Self::default().pull_registry);
+        self.proxy = profile.proxy.clone();
+    }
+    
+    /// 指定 profile 的配置项，生效中的 profile 返回当前值
+    pub fn profile(&self, name: &str) -> Option<Profile> {
+        if self.active_profile.as_deref() == Some(name) {
+            Some(self.profile_fields())
+        } else {
+            self.profiles.get(name).cloned()
+        }
+    }
+    
+    /// 创建或更新命名 profile
+    pub fn set_profile(&mut self, name: &str, profile: Profile) {
+        if self.active_profile.as_deref() == Some(name) {
+            self.apply_profile_fields(&profile);
+        } else {
+            self.profiles.insert(name.to_string(), profile);
+        }
+    }
+    
+    /// 删除命名 profile 及其保存的令牌
+    pub async fn remove_profile(&mut self, name: &str) -> Result<()> {
+        if self.active_profile.as_deref() == Some(name) {
+            return Err(anyhow::anyhow!("profile '{}' 正在使用，请先切换到其他 profile", name));
+        }
+        if self.profiles.remove(name).is_none() {
+            return Err(anyhow::anyhow!("profile '{}' 不存在", name));
+        }
+        self.credential_store.erase(name).await?;
+        if self.current_profile.as_deref() == Some(name) {
+            self.current_profile = None;
+        }
+        Ok(())
+    }
+    
+    /// 保存或清除 GitHub 令牌（写入 credential_store，不写入 config.json）
+    pub async fn set_token(&mut self, token: Option<Secret>) -> Result<()> {
+        match &token {
+            Some(token) => self.credential_store.store(self.profile_name(), token).await?,
+            None => self.credential_store.erase(self.profile_name()).await?,
+        }
+        self.token_source = token.as_ref().map(|_| TokenSource::Stored);
+        self.github_token = token;
+        self.token_resolved = true;
         Ok(())
     }
     
-    fn config_path() -> Result<PathBuf> {
+    /// 不读取凭据存储和 gh 即可确定的令牌来源，用于状态输出；外部命令和 gh 提供的令牌返回 None
+    pub fn token_hint(&self) -> Option<TokenSource> {
+        if self.token_resolved {
+            return self.token_source;
+        }
+        if TOKEN_FLAG.get().is_some() {
+            return Some(TokenSource::Flag);
+        }
+        if let Some(name) = TOKEN_ENV_VARS
+            .iter()
+            .find(|name| std::env::var(name).is_ok_and(|token| !token.trim().is_empty()))
+        {
+            return Some(TokenSource::Env(name));
+        }
+        self.credential_store
+            .has_token(self.profile_name())
+            .then_some(TokenSource::Stored)
+    }
+    
+    /// GitHub 令牌明文，仅在发送请求时使用（需先 load_token）
+    pub fn token(&self) -> Option<&str> {
+        self.github_token.as_ref().map(Secret::expose)
+    }
+    
+    pub fn config_dir() -> Result<PathBuf> {
         let mut path = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
         path.push("docker-sync-cli");
+        Ok(path)
+    }
+    
+    pub fn config_path() -> Result<PathBuf> {
+        let mut path = Self::config_dir()?;
         path.push("config.json");
         Ok(path)
     }
     
-    #[allow(dead_code)]
+    /// docker 凭据助手提供令牌的主机：ghcr_registry 及显式配置的 helper_registries，
+    /// 默认不包括 pull_registry 等第三方镜像源
+    pub fn credential_helper_hosts(&self) -> Vec<String> {
+        let mut hosts = vec![registry_host(&self.ghcr_registry)];
+        for registry in &self.helper_registries {
+            let host = registry_host(registry);
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+        hosts
+    }
+    
     pub fn get_all_registries(&self) -> Vec<String> {
-        let mut registries = vec![
-            self.nju_registry.clone(),
-            self.ghcr_registry.clone(),
-        ];
-        registries.extend(self.custom_registries.clone());
+        let mut registries = vec![self.pull_registry.clone()];
+        for registry in std::iter::once(&self.ghcr_registry).chain(&self.custom_registries) {
+            if !registries.contains(registry) {
+                registries.push(registry.clone());
+            }
+        }
         registries
     }
 }
@@ -60,12 +475,185 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             github_token: None,
+            token_source: None,
+            token_resolved: false,
+            legacy_token: None,
+            credential_store: CredentialStore::default(),
+            github_app: None,
             ghcr_registry: "ghcr.io".to_string(),
-            nju_registry: "ghcr.nju.edu.cn".to_string(),
-            default_registry: "ghcr.nju.edu.cn".to_string(),
+            pull_registry: "ghcr.nju.edu.cn".to_string(),
             custom_registries: vec![],
             proxy: None,
+            proxy_rules: BTreeMap::new(),
+            connect_timeout: 10,
+            request_timeout: 120,
+            ca_bundles: vec![],
+            helper_registries: vec![],
+            owner: None,
+            repo: None,
+            oauth_client_id: None,
+            runs_on: vec![],
+            profiles: BTreeMap::new(),
+            current_profile: None,
+            active_profile: None,
+            top_level: None,
+            project_images: vec![],
+            origins: BTreeMap::new(),
+            layered: BTreeMap::new(),
+            base: None,
+        }
+    }
+}
+
+/// 将旧版本的配置升级到 CONFIG_VERSION，返回原版本号（无需迁移时为 None）
+fn migrate(value: &mut serde_json::Value) -> Result<Option<u32>> {
+    let config = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("配置文件的顶层必须是 JSON 对象"))?;
+    let version = match config.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("配置文件的 version 字段无效: {}", version))? as u32,
+    };
+    if version > CONFIG_VERSION {
+        // 继续使用会在保存时降级文件并丢弃新版本的配置项
+        return Err(anyhow::anyhow!(
+            "配置文件版本 {} 高于当前支持的 {}，请升级 docker-sync",
+            version,
+            CONFIG_VERSION
+        ));
+    }
+    if version == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    // 1 -> 2：nju_registry（实际使用）和 default_registry（仅显示）合并为 pull_registry
+    if version < 2 {
+        let nju_registry = config.remove("nju_registry");
+        let default_registry = config.remove("default_registry");
+        if let Some(registry) = nju_registry.or(default_registry) {
+            config.entry("pull_registry").or_insert(registry);
+        }
+    }
+
+    config.insert("version".to_string(), CONFIG_VERSION.into());
+    Ok(Some(version))
+}
+
+/// 迁移前的备份文件，例如 config.json.v1.bak
+fn backup_path(config_path: &Path, version: u32) -> PathBuf {
+    config_path.with_extension(format!("json.v{}.bak", version))
+}
+
+/// 备份的内容：原配置去掉明文令牌（令牌随后迁移到凭据存储）
+fn backup_content(content: &str) -> Result<String> {
+    let mut original: serde_json::Value = serde_json::from_str(content)?;
+    if let Some(original) = original.as_object_mut() {
+        original.remove("github_token");
+    }
+    Ok(serde_json::to_string_pretty(&original)?)
+}
+
+/// 镜像源地址中的主机名，例如 https://ghcr.io/v2/ -> ghcr.io
+pub fn registry_host(server_url: &str) -> String {
+    let url = server_url.trim();
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    url.split('/').next().unwrap_or(url).to_lowercase()
+}
+
+/// `gh auth token` 的输出，gh 未安装或未登录时返回 None
+async fn gh_cli_token() -> Option<Secret> {
+    let output = tokio::process::Command::new("gh")
+        .args(["auth", "token", "--hostname", "github.com"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?;
+    let token = token.trim();
+    (!token.is_empty()).then(|| Secret::new(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_nju_registry_to_pull_registry() {
+        let (config, migrated) =
+            Config::parse(r#"{"nju_registry": "mirror.example.com", "default_registry": "ghcr.io"}"#).unwrap();
+        assert_eq!(migrated, Some(1));
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.pull_registry, "mirror.example.com");
+    }
+
+    #[test]
+    fn migrates_default_registry_when_nju_registry_is_missing() {
+        let (config, migrated) = Config::parse(r#"{"default_registry": "ghcr.io"}"#).unwrap();
+        assert_eq!(migrated, Some(1));
+        assert_eq!(config.pull_registry, "ghcr.io");
+    }
+
+    #[test]
+    fn migration_keeps_existing_pull_registry() {
+        let (config, _) =
+            Config::parse(r#"{"pull_registry": "ghcr.io", "nju_registry": "ghcr.nju.edu.cn"}"#).unwrap();
+        assert_eq!(config.pull_registry, "ghcr.io");
+    }
+
+    #[test]
+    fn current_version_is_not_migrated() {
+        let (config, migrated) = Config::parse(r#"{"version": 2, "pull_registry": "ghcr.io"}"#).unwrap();
+        assert_eq!(migrated, None);
+        assert_eq!(config.pull_registry, "ghcr.io");
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let error = Config::parse(r#"{"version": 3, "pull_registry": "ghcr.io"}"#).unwrap_err();
+        assert!(error.to_string().contains("版本 3"));
+    }
+
+    #[test]
+    fn explicit_and_stored_tokens_take_precedence_over_github_app() {
+        let mut config = Config::default();
+        for source in [TokenSource::Flag, TokenSource::Env("DOCKER_SYNC_TOKEN"), TokenSource::Stored] {
+            config.token_source = Some(source);
+            assert!(!config.prefers_github_app(), "{:?}", source);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn github_app_is_used_without_token_or_with_gh_cli_token() {
+        let mut config = Config::default();
+        assert!(config.prefers_github_app());
+        config.token_source = Some(TokenSource::GhCli);
+        assert!(config.prefers_github_app());
+    }
+
+    #[test]
+    fn backup_path_includes_original_version() {
+        let path = Path::new("/home/user/.config/docker-sync-cli/config.json");
+        assert_eq!(
+            backup_path(path, 1),
+            Path::new("/home/user/.config/docker-sync-cli/config.json.v1.bak")
+        );
+    }
+
+    #[test]
+    fn backup_drops_plaintext_token() {
+        let backup = backup_content(r#"{"github_token": "ghp_secret", "nju_registry": "ghcr.nju.edu.cn"}"#).unwrap();
+        assert!(!backup.contains("ghp_secret"));
+        assert!(backup.contains("nju_registry"));
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::{validate_profile_name, Config, DEFAULT_PROFILE};
+
+/// 加密令牌文件的口令环境变量，设置后不再交互询问
+const PASSPHRASE_ENV: &str = "DOCKER_SYNC_PASSPHRASE";
+
+/// 本进程内已输入的口令，避免多次加载配置时重复询问
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+/// 敏感字符串，Debug/Display 均输出为 ***，避免令牌出现在日志中
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+/// GitHub 令牌的存储方式
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CredentialStore {
+    /// 配置目录下仅当前用户可读写 (0600) 的 token 文件
+    #[default]
+    File,
+    /// 用口令派生密钥 (Argon2) 加密的 token.enc 文件
+    Encrypted,
+    /// 外部命令，例如 `pass show docker-sync/github-token`
+    Helper {
+        get: String,
+        store: Option<String>,
+        erase: Option<String>,
+    },
+}
+
+/// token.enc 的内容
+#[derive(Serialize, Deserialize)]
+struct EncryptedToken {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl CredentialStore {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CredentialStore::File => "file",
+            CredentialStore::Encrypted => "encrypted",
+            CredentialStore::Helper { .. } => "helper",
+        }
+    }
+
+    /// 存储方式及其位置，用于状态输出
+    pub fn describe(&self, profile: &str) -> String {
+        let location = match self {
+            CredentialStore::File => token_path(profile, "").map(|path| path.display().to_string()),
+            CredentialStore::Encrypted => token_path(profile, ".enc").map(|path| path.display().to_string()),
+            CredentialStore::Helper { get, .. } => Ok(get.clone()),
+        };
+        format!("{} ({})", self.name(), location.unwrap_or_default())
+    }
+
+    /// 读取指定 profile 的令牌，未保存时返回 None
+    pub async fn load(&self, profile: &str) -> Result<Option<Secret>> {
+        match self {
+            CredentialStore::File => {
+                let path = token_path(profile, "")?;
+                if !path.exists() {
+                    return Ok(None);
+                }
+                let token = fs::read_to_string(&path).await?;
+                let token = token.trim();
+                Ok((!token.is_empty()).then(|| Secret::new(token)))
+            }
+            CredentialStore::Encrypted => {
+                let path = token_path(profile, ".enc")?;
+                if !path.exists() {
+                    return Ok(None);
+                }
+                let content = fs::read_to_string(&path).await?;
+                let encrypted: EncryptedToken = serde_json::from_str(&content)?;
+                let engine = base64::engine::general_purpose::STANDARD;
+                let salt = engine.decode(&encrypted.salt)?;
+                let nonce = engine.decode(&encrypted.nonce)?;
+                let ciphertext = engine.decode(&encrypted.ciphertext)?;
+                if nonce.len() != 12 {
+                    return Err(anyhow!("Corrupted encrypted token file: {}", path.display()));
+                }
+
+                let passphrase = passphrase(false)?;
+                let cipher = ChaCha20Poly1305::new(&derive_key(&passphrase, &salt)?.into());
+                match cipher.decrypt(nonce.as_slice().into(), ciphertext.as_slice()) {
+                    Ok(plaintext) => Ok(Some(Secret::new(String::from_utf8(plaintext)?))),
+                    Err(_) => {
+                        // 口令错误时清除缓存，下次重新询问
+                        *PASSPHRASE.lock().unwrap() = None;
+                        Err(anyhow!("Wrong passphrase for {}", path.display()))
+                    }
+                }
+            }
+            CredentialStore::Helper { get, .. } => {
+                let output = run_helper(get, profile, None).await?;
+                let token = output.lines().next().unwrap_or("").trim();
+                Ok((!token.is_empty()).then(|| Secret::new(token)))
+            }
+        }
+    }
+
+    pub async fn store(&self, profile: &str, token: &Secret) -> Result<()> {
+        match self {
+            CredentialStore::File => {
+                write_private_file(&token_path(profile, "")?, token.expose().as_bytes()).await
+            }
+            CredentialStore::Encrypted => {
+                let passphrase = passphrase(true)?;
+                let mut salt = [0u8; 16];
+                chacha20poly1305::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut salt);
+                let cipher = ChaCha20Poly1305::new(&derive_key(&passphrase, &salt)?.into());
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let ciphertext = cipher
+                    .encrypt(&nonce, token.expose().as_bytes())
+                    .map_err(|_| anyhow!("Failed to encrypt token"))?;
+
+                let engine = base64::engine::general_purpose::STANDARD;
+                let encrypted = EncryptedToken {
+                    salt: engine.encode(salt),
+                    nonce: engine.encode(nonce),
+                    ciphertext: engine.encode(ciphertext),
+                };
+                let content = serde_json::to_string_pretty(&encrypted)?;
+                write_private_file(&token_path(profile, ".enc")?, content.as_bytes()).await
+            }
+            CredentialStore::Helper { store, .. } => match store {
+                Some(command) => run_helper(command, profile, Some(token.expose())).await.map(|_| ()),
+                None => Err(anyhow!("Credential helper has no store command; save the token with the helper itself")),
+            },
+        }
+    }
+
+    /// 是否保存了令牌（不解密、不运行外部命令，外部命令存储时总是返回 false）
+    pub fn has_token(&self, profile: &str) -> bool {
+        let path = match self {
+            CredentialStore::File => token_path(profile, ""),
+            CredentialStore::Encrypted => token_path(profile, ".enc"),
+            CredentialStore::Helper { .. } => return false,
+        };
+        path.is_ok_and(|path| path.exists())
+    }
+
+    /// 删除已保存的令牌，不存在时忽略
+    pub async fn erase(&self, profile: &str) -> Result<()> {
+        match self {
+            CredentialStore::File => remove_if_exists(&token_path(profile, "")?).await,
+            CredentialStore::Encrypted => remove_if_exists(&token_path(profile, ".enc")?).await,
+            CredentialStore::Helper { erase, .. } => match erase {
+                Some(command) => run_helper(command, profile, None).await.map(|_| ()),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+/// 默认 profile 为 token / token.enc，其他 profile 为 token-<名称> / token-<名称>.enc
+fn token_path(profile: &str, extension: &str) -> Result<PathBuf> {
+    validate_profile_name(profile)?;
+    let mut path = Config::config_dir()?;
+    if profile == DEFAULT_PROFILE {
+        path.push(format!("token{}", extension));
+    } else {
+        path.push(format!("token-{}{}", profile, extension));
+    }
+    Ok(path)
+}
+
+/// 写入仅当前用户可读写的文件（Unix 下权限为 0600）
+pub async fn write_private_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path).await?;
+    // 已存在的文件不会应用 mode，需要单独收紧权限
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    }
+    file.write_all(content).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 优先使用环境变量中的口令；保存时交互输入需要确认一次
+fn passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if let Some(passphrase) = PASSPHRASE.lock().unwrap().clone() {
+        return Ok(passphrase);
+    }
+
+    let passphrase = rpassword::prompt_password("Token passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("Passphrase cannot be empty"));
+    }
+    if confirm && rpassword::prompt_password("Confirm passphrase: ")? != passphrase {
+        return Err(anyhow!("Passphrases do not match"));
+    }
+
+    *PASSPHRASE.lock().unwrap() = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+/// 通过 shell 执行凭据助手命令，profile 通过 DOCKER_SYNC_PROFILE 环境变量传递，input 写入标准输入，返回标准输出
+async fn run_helper(command: &str, profile: &str, input: Option<&str>) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .env("DOCKER_SYNC_PROFILE", profile)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run credential helper '{}': {}", command, e))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+    }
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Credential helper '{}' failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -0,0 +1,281 @@
+use anyhow::Result;
+use colored::*;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// 同步失败的已知原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    RateLimited,
+    ManifestUnknown,
+    MissingPackagesWrite,
+    PushDenied,
+    DiskFull,
+    Timeout,
+}
+
+impl FailureKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            FailureKind::RateLimited => "Docker Hub 拉取频率受限 (toomanyrequests)",
+            FailureKind::ManifestUnknown => "源镜像或标签不存在 (manifest unknown)",
+            FailureKind::MissingPackagesWrite => "工作流缺少 packages: write 权限",
+            FailureKind::PushDenied => "推送到 GHCR 被拒绝 (denied)",
+            FailureKind::DiskFull => "Runner 磁盘空间不足",
+            FailureKind::Timeout => "工作流执行超时",
+        }
+    }
+
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            FailureKind::RateLimited => {
+                "GitHub 托管 Runner 共享出口 IP，匿名拉取很容易触发 Docker Hub 的频率限制"
+            }
+            FailureKind::ManifestUnknown => {
+                "源仓库中找不到该镜像或标签，或镜像为私有镜像需要登录"
+            }
+            FailureKind::MissingPackagesWrite => {
+                "GITHUB_TOKEN 没有写入 package 的权限，无法推送镜像"
+            }
+            FailureKind::PushDenied => {
+                "GHCR 拒绝了推送，通常是同名 package 已存在且未授权给 docker-sync 仓库"
+            }
+            FailureKind::DiskFull => "镜像解压后超出了 Runner 的可用磁盘空间",
+            FailureKind::Timeout => "工作流运行时间超过了 GitHub Actions 的最大执行时长",
+        }
+    }
+
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            FailureKind::RateLimited => {
+                "稍后重试，或配置 Docker Hub 登录凭据: docker-sync secrets set docker.io"
+            }
+            FailureKind::ManifestUnknown => {
+                "检查镜像名和标签拼写；私有镜像请先运行 docker-sync secrets set <registry>"
+            }
+            FailureKind::MissingPackagesWrite => {
+                "在仓库 Settings → Actions → General 中启用 'Read and write permissions'"
+            }
+            FailureKind::PushDenied => {
+                "在 package 设置的 Manage Actions access 中添加 docker-sync 仓库并授予 Write 权限，或删除该 package 后重试"
+            }
+            FailureKind::DiskFull => "改用磁盘更大的 Runner（如自托管 Runner）同步该镜像",
+            FailureKind::Timeout => "大镜像请重试，或改用网络更好的 Runner",
+        }
+    }
+
+    /// 判断单行日志是否命中该失败原因
+    fn matches(&self, line: &str) -> bool {
+        let lower = line.to_lowercase();
+        match self {
+            FailureKind::RateLimited => {
+                lower.contains("toomanyrequests") || lower.contains("pull rate limit")
+            }
+            FailureKind::ManifestUnknown => {
+                lower.contains("manifest unknown")
+                    || (lower.contains("manifest for") && lower.contains("not found"))
+                    || lower.contains("pull access denied")
+            }
+            FailureKind::MissingPackagesWrite => {
+                lower.contains("denied")
+                    && (lower.contains("write_package")
+                        || lower.contains("installation not allowed to write"))
+            }
+            FailureKind::PushDenied => {
+                lower.contains("denied")
+                    && !lower.contains("pull access denied")
+                    && !lower.contains("toomanyrequests")
+            }
+            FailureKind::DiskFull => lower.contains("no space left on device"),
+            FailureKind::Timeout => {
+                lower.contains("exceeded the maximum execution time")
+                    || lower.contains("has timed out")
+            }
+        }
+    }
+}
+
+/// 匹配优先级：越具体的原因越靠前
+const KNOWN_FAILURES: [FailureKind; 6] = [
+    FailureKind::RateLimited,
+    FailureKind::ManifestUnknown,
+    FailureKind::MissingPackagesWrite,
+    FailureKind::PushDenied,
+    FailureKind::DiskFull,
+    FailureKind::Timeout,
+];
+
+pub struct Diagnosis {
+    pub kind: FailureKind,
+    /// 命中的日志行，用于展示证据
+    pub evidence: String,
+}
+
+/// 根据 run 的结论和 job 日志归纳失败原因，每类原因只保留第一条证据
+pub fn diagnose(logs: &str, conclusion: &str) -> Vec<Diagnosis> {
+    let mut diagnoses: Vec<Diagnosis> = Vec::new();
+
+    for line in logs.lines() {
+        let kind = match KNOWN_FAILURES.iter().find(|kind| kind.matches(line)) {
+            Some(kind) => *kind,
+            None => continue,
+        };
+
+        if diagnoses.iter().any(|d| d.kind == kind) {
+            continue;
+        }
+
+        // 权限缺失已经解释了 denied，不再重复报告推送被拒绝
+        if kind == FailureKind::PushDenied
+            && diagnoses.iter().any(|d| d.kind == FailureKind::MissingPackagesWrite)
+        {
+            continue;
+        }
+
+        diagnoses.push(Diagnosis {
+            kind,
+            evidence: strip_timestamp(line).trim().to_string(),
+        });
+    }
+
+    if conclusion == "timed_out" && !diagnoses.iter().any(|d| d.kind == FailureKind::Timeout) {
+        diagnoses.push(Diagnosis {
+            kind: FailureKind::Timeout,
+            evidence: format!("run conclusion: {}", conclusion),
+        });
+    }
+
+    diagnoses
+}
+
+/// 输出诊断结果；未识别时展示日志末尾几行
+pub fn print_diagnosis(diagnoses: &[Diagnosis], logs: &str) {
+    if diagnoses.is_empty() {
+        println!("\n{}", "📋 未识别的失败原因，日志末尾:".red());
+        let tail: Vec<&str> = logs
+            .lines()
+            .map(strip_timestamp)
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        for line in &tail[tail.len().saturating_sub(10)..] {
+            println!("  {}", line.red());
+        }
+        return;
+    }
+
+    println!("\n{}", "🔎 失败诊断:".red());
+    for diagnosis in diagnoses {
+        println!("  {} {}", "•".red(), diagnosis.kind.title().red().bold());
+        println!("    原因: {}", diagnosis.kind.explanation());
+        println!("    建议: {}", diagnosis.kind.suggestion().cyan());
+        println!("    日志: {}", diagnosis.evidence.dimmed());
+    }
+}
+
+/// 保存完整的运行日志，返回文件路径
+pub async fn save_run_log(run_id: u64, logs: &str) -> Result<PathBuf> {
+    let mut path = dirs::cache_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+    path.push("docker-sync-cli");
+    path.push("logs");
+    fs::create_dir_all(&path).await?;
+
+    path.push(format!("run-{}.log", run_id));
+    fs::write(&path, logs).await?;
+    Ok(path)
+}
+
+/// 去掉 GitHub Actions 日志行首的时间戳（如 2024-01-01T00:00:00.0000000Z）
+pub fn strip_timestamp(line: &str) -> &str {
+    let line = line.trim_start_matches('\u{feff}');
+    match line.split_once(' ') {
+        Some((prefix, rest)) if prefix.len() >= 20 && prefix.ends_with('Z') && prefix.contains('T') => rest,
+        _ => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(logs: &str, conclusion: &str) -> Vec<FailureKind> {
+        diagnose(logs, conclusion).into_iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn detects_docker_hub_rate_limit() {
+        let logs = "2024-05-01T10:00:00.1234567Z Error response from daemon: toomanyrequests: You have reached your pull rate limit. You may increase the limit by authenticating and upgrading: https://www.docker.com/increase-rate-limit";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::RateLimited]);
+    }
+
+    #[test]
+    fn detects_missing_image_or_tag() {
+        let logs = "2024-05-01T10:00:00.1234567Z Error response from daemon: manifest for nginx:does-not-exist not found: manifest unknown: manifest unknown";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::ManifestUnknown]);
+
+        let logs = "Error response from daemon: pull access denied for private/app, repository does not exist or may require 'docker login': denied: requested access to the resource is denied";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::ManifestUnknown]);
+    }
+
+    #[test]
+    fn missing_packages_write_replaces_push_denied() {
+        let logs = "\
+2024-05-01T10:01:00.0000000Z The push refers to repository [ghcr.io/octocat/nginx]
+2024-05-01T10:01:01.0000000Z denied: installation not allowed to Write organization package
+2024-05-01T10:01:01.1000000Z Error: denied: denied";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::MissingPackagesWrite]);
+
+        let logs = "denied: permission_denied: write_package";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::MissingPackagesWrite]);
+    }
+
+    #[test]
+    fn detects_push_denied() {
+        let logs = "2024-05-01T10:01:01.0000000Z denied: permission_denied: The requested installation does not exist.";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::PushDenied]);
+    }
+
+    #[test]
+    fn detects_full_disk() {
+        let logs = "2024-05-01T10:05:00.0000000Z failed to register layer: write /usr/lib/x86_64-linux-gnu/libLLVM-15.so.1: no space left on device";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::DiskFull]);
+    }
+
+    #[test]
+    fn detects_timeout_from_logs_or_conclusion() {
+        let logs = "2024-05-01T16:00:00.0000000Z ##[error]The job running on runner GitHub Actions 2 has exceeded the maximum execution time of 360 minutes.";
+        assert_eq!(kinds(logs, "failure"), [FailureKind::Timeout]);
+
+        let diagnoses = diagnose("Pushing image: ghcr.io/octocat/nginx:latest", "timed_out");
+        assert_eq!(diagnoses.len(), 1);
+        assert_eq!(diagnoses[0].kind, FailureKind::Timeout);
+        assert_eq!(diagnoses[0].evidence, "run conclusion: timed_out");
+    }
+
+    #[test]
+    fn reports_each_kind_once_with_first_evidence() {
+        let logs = "\
+2024-05-01T10:00:00.0000000Z Error response from daemon: toomanyrequests: first
+2024-05-01T10:00:10.0000000Z Error response from daemon: toomanyrequests: second
+2024-05-01T10:00:20.0000000Z write /var/lib/docker/tmp: no space left on device";
+        let diagnoses = diagnose(logs, "failure");
+        assert_eq!(
+            diagnoses.iter().map(|d| d.kind).collect::<Vec<_>>(),
+            [FailureKind::RateLimited, FailureKind::DiskFull]
+        );
+        assert_eq!(diagnoses[0].evidence, "Error response from daemon: toomanyrequests: first");
+    }
+
+    #[test]
+    fn unrelated_logs_are_not_diagnosed() {
+        let logs = "2024-05-01T10:00:00.0000000Z Pulling image: nginx:alpine\n2024-05-01T10:00:05.0000000Z Status: Downloaded newer image for nginx:alpine";
+        assert!(kinds(logs, "failure").is_empty());
+    }
+
+    #[test]
+    fn strip_timestamp_only_removes_actions_timestamps() {
+        assert_eq!(strip_timestamp("2024-05-01T10:00:00.1234567Z Pulling image"), "Pulling image");
+        assert_eq!(strip_timestamp("\u{feff}2024-05-01T10:00:00.1234567Z done"), "done");
+        assert_eq!(strip_timestamp("Pulling image nginx"), "Pulling image nginx");
+    }
+}
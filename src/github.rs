@@ -3,20 +3,161 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use base64::Engine;
+use chrono::{DateTime, Utc};
 use colored::*;
 
+use crate::auth::GitHubApp;
+use crate::config::Config;
+use crate::http;
+
+/// GitHub API 地址，用于提示访问 API 时使用的代理
+const GITHUB_API: &str = "https://api.github.com";
+
+/// 源仓库凭据 secret 的名称前缀，由 secrets set 写入
+pub const REGISTRY_SECRET_PREFIX: &str = "SRC_REGISTRY_";
+
+/// 默认的同步仓库名称
+const DEFAULT_SYNC_REPO: &str = "docker-sync";
+
+/// 定时同步读取的跟踪镜像列表（同步仓库根目录下的 JSON 数组）
+pub const TRACKED_IMAGES_FILE: &str = "tracked-images.json";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorkflowRun {
     pub id: u64,
     pub status: String,
     pub conclusion: Option<String>,
+    /// 工作流的 run-name，形如 "Sync nginx:alpine"
+    pub display_title: Option<String>,
+    pub html_url: Option<String>,
+    /// 触发事件：workflow_dispatch（手动同步）或 schedule（定时同步）
+    pub event: Option<String>,
+    pub triggering_actor: Option<User>,
+    pub run_attempt: Option<u64>,
+    pub run_started_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl WorkflowRun {
+    /// 从 run-name 中解析同步的镜像，批量同步时为 JSON 列表（旧版工作流没有 run-name，返回空列表）
+    pub fn images(&self) -> Vec<String> {
+        let input = match self
+            .display_title
+            .as_deref()
+            .and_then(|title| title.strip_prefix("Sync "))
+            .map(str::trim)
+        {
+            Some(input) if !input.is_empty() => input,
+            _ => return vec![],
+        };
+        
+        if input.starts_with('[') {
+            serde_json::from_str(input).unwrap_or_default()
+        } else {
+            vec![input.to_string()]
+        }
+    }
+    
+    /// 运行时长：已完成的按最后更新时间计算，运行中的按当前时间计算
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        let started = self.run_started_at?;
+        let finished = if self.status == "completed" {
+            self.updated_at?
+        } else {
+            Utc::now()
+        };
+        Some(finished - started)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorkflowRunsResponse {
+    #[serde(default)]
+    pub total_count: u64,
     pub workflow_runs: Vec<WorkflowRun>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: u64,
+    pub name: String,
+    pub archive_download_url: String,
+    #[serde(default)]
+    pub expired: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArtifactsResponse {
+    pub artifacts: Vec<Artifact>,
+}
+
+/// 工作流上传的 sync-result.json
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncResult {
+    pub image: String,
+    pub target: String,
+    pub source_digest: String,
+    pub target_digest: String,
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    #[serde(default)]
+    pub image_size: u64,
+    #[serde(default)]
+    pub bytes_pushed: u64,
+    #[serde(default)]
+    pub timings: SyncTimings,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncTimings {
+    pub pull_seconds: u64,
+    pub push_seconds: u64,
+    pub total_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Package {
+    pub id: u64,
+    pub name: String,
+    pub visibility: Option<String>,
+    pub html_url: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageVersion {
+    pub id: u64,
+    /// 对于 container package 是 manifest 摘要
+    pub name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub metadata: PackageVersionMetadata,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PackageVersionMetadata {
+    #[serde(default)]
+    pub container: ContainerMetadata,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContainerMetadata {
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl PackageVersion {
+    pub fn tags(&self) -> &[String] {
+        &self.metadata.container.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t == tag)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Repository {
@@ -24,6 +165,30 @@ pub struct Repository {
     pub name: String,
     pub full_name: String,
     pub html_url: String,
+    /// 当前令牌对该仓库的权限
+    #[serde(default)]
+    pub permissions: Option<RepositoryPermissions>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RepositoryPermissions {
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub push: bool,
+    #[serde(default)]
+    pub pull: bool,
+}
+
+/// 从 /user 响应头中读取的令牌信息
+pub struct TokenInfo {
+    pub login: String,
+    /// X-OAuth-Scopes；细粒度令牌和 GitHub App 没有该响应头
+    pub scopes: Option<Vec<String>>,
+    /// 细粒度令牌（github_pat_ 前缀）
+    pub fine_grained: bool,
+    /// GitHub-Authentication-Token-Expiration，未设置过期时间时为 None
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,48 +197,89 @@ pub struct User {
     pub id: u64,
 }
 
+/// GitHub API 的认证方式
+enum Credentials {
+    Token(String),
+    App(GitHubApp),
+}
+
 pub struct GitHubClient {
     client: Client,
-    token: String,
+    credentials: Credentials,
+    /// 同步目标账号：配置的 owner，未配置时为令牌所属用户
     username: Option<String>,
+    /// 配置的目标账号或组织
+    owner: Option<String>,
+    owner_is_org: bool,
+    /// 同步仓库名称
+    repo: String,
+    /// 工作流默认的 runs-on 标签，为空时使用 ubuntu-latest
+    runs_on: Vec<String>,
 }
 
 impl GitHubClient {
-    pub fn new(token: &str) -> Self {
-        Self {
-            client: Client::new(),
-            token: token.to_string(),
-            username: None,
+    pub fn new(token: &str, config: &Config) -> Result<Self> {
+        if let Some(proxy) = http::proxy_for(config, GITHUB_API) {
+            println!("{} 使用代理: {}", "🌐".blue(), proxy.as_str().cyan());
         }
+        Ok(Self {
+            client: http::client(config)?,
+            credentials: Credentials::Token(token.to_string()),
+            username: None,
+            owner: None,
+            owner_is_org: false,
+            repo: DEFAULT_SYNC_REPO.to_string(),
+            runs_on: Vec::new(),
+        })
     }
     
-    pub fn new_with_proxy(token: &str, proxy: Option<&str>) -> Self {
-        let mut builder = Client::builder();
-        
-        if let Some(proxy_url) = proxy {
-            match reqwest::Proxy::all(proxy_url) {
-                Ok(proxy) => {
-                    println!("{} 使用代理: {}", "🌐".blue(), proxy_url.cyan());
-                    builder = builder.proxy(proxy);
-                }
-                Err(e) => {
-                    println!("{} 代理配置错误: {} - {}", "⚠️".yellow(), proxy_url, e);
-                    println!("{} 将使用直连方式", "📡".yellow());
-                }
-            }
+    /// 以 GitHub App 身份访问 API，安装令牌在每次请求前按需刷新
+    pub fn new_app(app: GitHubApp, config: &Config) -> Result<Self> {
+        let mut client = Self::new("", config)?;
+        client.credentials = Credentials::App(app);
+        Ok(client)
+    }
+    
+    pub fn is_app(&self) -> bool {
+        matches!(self.credentials, Credentials::App(_))
+    }
+    
+    /// 当前使用的访问令牌（GitHub App 时为安装令牌）
+    pub async fn access_token(&self) -> Result<String> {
+        match &self.credentials {
+            Credentials::Token(token) => Ok(token.clone()),
+            Credentials::App(app) => Ok(app.installation_token(&self.client).await?.expose().to_string()),
         }
-        
-        Self {
-            client: match builder.build() {
-                Ok(client) => client,
-                Err(e) => {
-                    println!("{} 客户端创建失败: {}", "❌".red(), e);
-                    Client::new()
-                }
-            },
-            token: token.to_string(),
-            username: None,
+    }
+    
+    /// Authorization 请求头
+    async fn authorization(&self) -> Result<String> {
+        Ok(format!("Bearer {}", self.access_token().await?))
+    }
+    
+    /// 同步到指定账号或组织的指定仓库，None 时使用令牌所属用户和 docker-sync
+    pub fn with_target(mut self, owner: Option<&str>, repo: Option<&str>) -> Self {
+        self.owner = owner.map(str::to_string);
+        self.repo = repo.unwrap_or(DEFAULT_SYNC_REPO).to_string();
+        self
+    }
+    
+    pub fn with_runs_on(mut self, runs_on: &[String]) -> Self {
+        self.runs_on = runs_on.to_vec();
+        self
+    }
+    
+    /// 渲染工作流模板，写入默认的 runs-on 和源仓库凭据 secret
+    /// （只把列出的 secret 传给登录步骤，而不是整个 secrets 上下文）
+    fn render_workflow(&self, registry_secrets: &[String]) -> String {
+        let default_runs_on = runs_on_json(&self.runs_on).replace('\'', "''");
+        let mut secrets_env = format!("SOURCE_REGISTRY_SECRETS: \"{}\"", registry_secrets.join(" "));
+        for name in registry_secrets {
+            secrets_env.push_str(&format!("\n        {}: ${{{{ secrets.{} }}}}", name, name));
         }
+        include_str!("../workflow-template.yml")
+            .replace("__DEFAULT_RUNS_ON__", &default_runs_on)
+            .replace("SOURCE_REGISTRY_SECRETS: __SOURCE_REGISTRY_SECRETS__", &secrets_env)
     }
     
     pub async fn get_username(&mut self) -> Result<String> {
@@ -81,12 +287,38 @@ impl GitHubClient {
             return Ok(username.clone());
         }
         
+        // 配置了目标账号时，判断其是否为组织（组织使用 /orgs 下的 API）
+        if let Some(owner) = self.owner.clone() {
+            let response = self
+                .client
+                .get(format!("https://api.github.com/users/{}", owner))
+                .header("Authorization", self.authorization().await?)
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .await?;
+            
+            if !response.status().is_success() {
+                return Err(anyhow!("Failed to get account {}: {}", owner, response.status()));
+            }
+            
+            let account: serde_json::Value = response.json().await?;
+            self.owner_is_org = account["type"].as_str() == Some("Organization");
+            self.username = Some(owner.clone());
+            return Ok(owner);
+        }
+        
+        // 安装令牌不能访问 /user，使用 App 安装所在的账号
+        if let Credentials::App(app) = &self.credentials {
+            let (_, account) = app.installation(&self.client).await?;
+            self.username = Some(account.clone());
+            return Ok(account);
+        }
+        
         let response = self
             .client
             .get("https://api.github.com/user")
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
@@ -99,9 +331,110 @@ impl GitHubClient {
         Ok(user.login)
     }
     
+    /// 读取令牌的用户、scope 和过期时间
+    pub async fn token_info(&self) -> Result<TokenInfo> {
+        let response = self
+            .client
+            .get("https://api.github.com/user")
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get user info: {}", response.status()));
+        }
+        
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let scopes = header("x-oauth-scopes").map(|scopes| {
+            scopes
+                .split(',')
+                .map(|scope| scope.trim().to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect()
+        });
+        let expires_at = header("github-authentication-token-expiration")
+            .and_then(|value| parse_token_expiration(&value));
+        let fine_grained = matches!(&self.credentials, Credentials::Token(token) if token.starts_with("github_pat_"));
+        
+        let user: User = response.json().await?;
+        Ok(TokenInfo {
+            login: user.login,
+            // 细粒度令牌也会返回空的 X-OAuth-Scopes
+            scopes: if fine_grained { None } else { scopes },
+            fine_grained,
+            expires_at,
+        })
+    }
+    
+    /// 获取仓库信息（包含当前令牌的权限），仓库不存在或无权访问时返回 None
+    pub async fn get_repo(&self, repo_name: &str) -> Result<Option<Repository>> {
+        let url = format!("https://api.github.com/repos/{}", repo_name);
+        
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+        
+        if response.status().as_u16() == 404 {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get repository: {}", response.status()));
+        }
+        
+        Ok(Some(response.json().await?))
+    }
+    
+    /// 检查能否读取同步工作流（Actions 读权限）
+    pub async fn can_read_actions(&self, repo_name: &str) -> Result<bool> {
+        let url = format!(
+            "https://api.github.com/repos/{}/actions/workflows?per_page=1",
+            repo_name
+        );
+        
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+        
+        Ok(response.status().is_success())
+    }
+    
+    /// 检查能否访问 Packages API
+    pub async fn can_list_packages(&self) -> Result<bool> {
+        let response = self
+            .client
+            .get("https://api.github.com/user/packages?package_type=container&per_page=1")
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+        
+        Ok(response.status().is_success())
+    }
+    
+    /// 同步仓库全名，形如 owner/docker-sync
+    pub async fn sync_repo(&mut self) -> Result<String> {
+        let owner = self.get_username().await?;
+        Ok(format!("{}/{}", owner, self.repo))
+    }
+    
     pub async fn ensure_sync_repo(&mut self) -> Result<String> {
         let username = self.get_username().await?;
-        let repo_name = format!("{}/docker-sync", username);
+        let repo_name = self.sync_repo().await?;
         
         // Check if repository exists
         if self.repo_exists(&repo_name).await? {
@@ -110,19 +443,42 @@ impl GitHubClient {
             return Ok(repo_name);
         }
         
+        // 安装令牌无法代表用户创建仓库
+        if self.is_app() {
+            return Err(anyhow!(
+                "Repository {} not found; create it and install the GitHub App on it first",
+                repo_name
+            ));
+        }
+        
         println!("{}", "🔧 首次使用：正在创建同步仓库（可能需要一些时间）...".blue());
         
         // Create repository
-        self.create_repo("docker-sync", &username).await?;
+        self.create_repo(&self.repo, &username).await?;
         
         // Upload workflow file
-        self.upload_workflow(&repo_name).await?;
+        self.upload_workflow(&repo_name, &self.render_workflow(&[])).await?;
         
         println!("{} 初始化完成！仓库地址: {}", "✅".green(), format!("https://github.com/{}", repo_name).cyan());
         Ok(repo_name)
     }
     
-    async fn ensure_workflow(&self, repo_name: &str) -> Result<()> {
+    /// 检查工作流文件是否存在且为最新，源仓库凭据变化后也需调用
+    pub async fn ensure_workflow(&self, repo_name: &str) -> Result<()> {
+        let registry_secrets = match self.list_actions_secrets(repo_name).await {
+            Ok(secrets) => Some(
+                secrets
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .filter(|name| name.starts_with(REGISTRY_SECRET_PREFIX))
+                    .collect::<Vec<_>>(),
+            ),
+            Err(e) => {
+                println!("{} 无法读取仓库 secret 列表: {}", "⚠️".yellow(), e);
+                None
+            }
+        };
+        
         // 检查workflow文件是否存在并更新
         let url = format!(
             "https://api.github.com/repos/{}/contents/.github/workflows/docker-sync.yml",
@@ -132,30 +488,44 @@ impl GitHubClient {
         let response = self
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
         
         if response.status().is_success() {
             // 获取现有文件的SHA用于更新
             let file_info: serde_json::Value = response.json().await?;
+            
+            // 内容未变化时无需更新
+            let existing = file_info["content"]
+                .as_str()
+                .map(|content| content.replace(['\n', '\r'], ""))
+                .and_then(|content| base64::engine::general_purpose::STANDARD.decode(content).ok());
+            // 无法确定凭据 secret 时保留现有工作流，避免丢失源仓库登录
+            let workflow = match &registry_secrets {
+                Some(names) => self.render_workflow(names),
+                None => return Ok(()),
+            };
+            if existing.as_deref() == Some(workflow.as_bytes()) {
+                return Ok(());
+            }
+            
             if let Some(sha) = file_info["sha"].as_str() {
                 println!("{} 正在更新工作流文件...", "📋".yellow());
-                self.update_workflow(repo_name, sha).await?;
+                self.update_workflow(repo_name, sha, &workflow).await?;
             }
         } else {
             // Workflow不存在，创建它
             println!("{} 工作流不存在，正在创建...", "📋".yellow());
-            self.upload_workflow(repo_name).await?;
+            let workflow = self.render_workflow(registry_secrets.as_deref().unwrap_or_default());
+            self.upload_workflow(repo_name, &workflow).await?;
         }
         
         Ok(())
     }
     
-    async fn update_workflow(&self, repo_name: &str, sha: &str) -> Result<()> {
-        let workflow_content = include_str!("../workflow-template.yml");
+    async fn update_workflow(&self, repo_name: &str, sha: &str, workflow_content: &str) -> Result<()> {
         let encoded_content = base64::engine::general_purpose::STANDARD.encode(workflow_content);
         
         let payload = json!({
@@ -173,9 +543,8 @@ impl GitHubClient {
         let response = self
             .client
             .put(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .json(&payload)
             .send()
             .await?;
@@ -196,9 +565,8 @@ impl GitHubClient {
         let response = self
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
@@ -216,12 +584,17 @@ impl GitHubClient {
             "has_wiki": false
         });
         
+        let url = if self.owner_is_org {
+            format!("https://api.github.com/orgs/{}/repos", username)
+        } else {
+            "https://api.github.com/user/repos".to_string()
+        };
+        
         let response = self
             .client
-            .post("https://api.github.com/user/repos")
-            .header("Authorization", format!("Bearer {}", self.token))
+            .post(&url)
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .json(&payload)
             .send()
             .await?;
@@ -251,9 +624,8 @@ impl GitHubClient {
         let _ = self
             .client
             .put(&enable_url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .json(&enable_payload)
             .send()
             .await;
@@ -272,9 +644,8 @@ impl GitHubClient {
         let response = self
             .client
             .put(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .json(&payload)
             .send()
             .await?;
@@ -291,10 +662,9 @@ impl GitHubClient {
         Ok(())
     }
     
-    async fn upload_workflow(&self, repo_name: &str) -> Result<()> {
+    async fn upload_workflow(&self, repo_name: &str, workflow_content: &str) -> Result<()> {
         println!("{} 正在配置 GitHub Action 工作流...", "📋".yellow());
         
-        let workflow_content = include_str!("../workflow-template.yml");
         let encoded_content = base64::engine::general_purpose::STANDARD.encode(workflow_content);
         
         let payload = json!({
@@ -311,9 +681,8 @@ impl GitHubClient {
         let response = self
             .client
             .put(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .json(&payload)
             .send()
             .await?;
@@ -338,7 +707,85 @@ impl GitHubClient {
         Ok(())
     }
     
-    pub async fn trigger_sync(&mut self, image: &str) -> Result<u64> {
+    /// 读取同步仓库中的跟踪镜像列表及文件 SHA（文件不存在时返回空列表）
+    pub async fn get_tracked_images(&self, repo_name: &str) -> Result<(Vec<String>, Option<String>)> {
+        let url = format!(
+            "https://api.github.com/repos/{}/contents/{}",
+            repo_name, TRACKED_IMAGES_FILE
+        );
+        
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+        
+        if response.status().as_u16() == 404 {
+            return Ok((vec![], None));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get tracked images: {}", response.status()));
+        }
+        
+        let file_info: serde_json::Value = response.json().await?;
+        let content = file_info["content"]
+            .as_str()
+            .map(|content| content.replace(['\n', '\r'], ""))
+            .unwrap_or_default();
+        let decoded = base64::engine::general_purpose::STANDARD.decode(content)?;
+        let images: Vec<String> = serde_json::from_slice(&decoded)
+            .map_err(|e| anyhow!("Invalid {}: {}", TRACKED_IMAGES_FILE, e))?;
+        
+        Ok((images, file_info["sha"].as_str().map(str::to_string)))
+    }
+    
+    /// 提交跟踪镜像列表，sha 为读取时的文件 SHA（新建文件时为 None）
+    pub async fn save_tracked_images(
+        &self,
+        repo_name: &str,
+        images: &[String],
+        sha: Option<&str>,
+        message: &str,
+    ) -> Result<()> {
+        let content = format!("{}\n", serde_json::to_string_pretty(images)?);
+        let mut payload = json!({
+            "message": message,
+            "content": base64::engine::general_purpose::STANDARD.encode(content),
+            "branch": "main"
+        });
+        if let Some(sha) = sha {
+            payload["sha"] = json!(sha);
+        }
+        
+        let url = format!(
+            "https://api.github.com/repos/{}/contents/{}",
+            repo_name, TRACKED_IMAGES_FILE
+        );
+        
+        let response = self
+            .client
+            .put(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .json(&payload)
+            .send()
+            .await?;
+        
+        if response.status().as_u16() == 409 {
+            return Err(anyhow!("{} was modified concurrently, please retry", TRACKED_IMAGES_FILE));
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to save tracked images: {}", error_text));
+        }
+        
+        Ok(())
+    }
+    
+    /// 触发同步，多个镜像在同一个 run 中以矩阵并行同步；runs_on 不为空时覆盖默认的 Runner 标签
+    pub async fn trigger_sync(&mut self, images: &[&str], runs_on: &[String]) -> Result<u64> {
         let repo_name = self.ensure_sync_repo().await?;
         
         let url = format!(
@@ -346,11 +793,21 @@ impl GitHubClient {
             repo_name
         );
         
+        // 单个镜像保持原样，便于在 run-name 中阅读
+        let docker_images = match images {
+            [image] => image.to_string(),
+            images => json!(images).to_string(),
+        };
+        let mut inputs = json!({
+            "docker_images": docker_images
+        });
+        if !runs_on.is_empty() {
+            inputs["runs_on"] = json!(runs_on_json(runs_on));
+        }
+        
         let payload = json!({
             "ref": "main",
-            "inputs": {
-                "docker_images": image
-            }
+            "inputs": inputs
         });
         
         // 重试逻辑，等待 workflow 被 GitHub 识别
@@ -359,9 +816,8 @@ impl GitHubClient {
             let response = self
                 .client
                 .post(&url)
-                .header("Authorization", format!("Bearer {}", self.token))
+                .header("Authorization", self.authorization().await?)
                 .header("Accept", "application/vnd.github.v3+json")
-                .header("User-Agent", "docker-sync-cli")
                 .json(&payload)
                 .send()
                 .await?;
@@ -389,30 +845,99 @@ impl GitHubClient {
     }
     
     async fn get_latest_run_id(&self, repo_name: &str) -> Result<u64> {
+        // 跳过同一时间启动的定时同步
+        let runs = self.list_workflow_runs(repo_name, None, 1, 10).await?;
+        
+        runs.workflow_runs
+            .iter()
+            .find(|run| run.event.as_deref() != Some("schedule"))
+            .map(|run| run.id)
+            .ok_or_else(|| anyhow!("No workflow runs found"))
+    }
+    
+    /// 分页获取同步工作流的运行记录，status 可以是 queued/in_progress/completed 或 success/failure 等结论
+    pub async fn list_workflow_runs(
+        &self,
+        repo_name: &str,
+        status: Option<&str>,
+        page: u32,
+        per_page: u32,
+    ) -> Result<WorkflowRunsResponse> {
+        let mut url = format!(
+            "https://api.github.com/repos/{}/actions/workflows/docker-sync.yml/runs?per_page={}&page={}",
+            repo_name, per_page, page
+        );
+        if let Some(status) = status {
+            url.push_str(&format!("&status={}", status));
+        }
+        
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get workflow runs: {}", response.status()));
+        }
+        
+        Ok(response.json().await?)
+    }
+    
+    pub async fn get_run(&self, run_id: u64, repo_name: &str) -> Result<WorkflowRun> {
         let url = format!(
-            "https://api.github.com/repos/{}/actions/workflows/docker-sync.yml/runs?per_page=1",
-            repo_name
+            "https://api.github.com/repos/{}/actions/runs/{}",
+            repo_name, run_id
         );
         
         let response = self
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
+        if response.status().as_u16() == 404 {
+            return Err(anyhow!("Workflow run {} not found in {}", run_id, repo_name));
+        }
         if !response.status().is_success() {
-            return Err(anyhow!("Failed to get workflow runs"));
+            return Err(anyhow!("Failed to get workflow run: {}", response.status()));
         }
         
-        let runs: WorkflowRunsResponse = response.json().await?;
+        Ok(response.json().await?)
+    }
+    
+    /// 在原 run 上重新运行：failed_only 为 true 时只重跑失败的 job
+    pub async fn rerun_workflow(&self, run_id: u64, repo_name: &str, failed_only: bool) -> Result<()> {
+        let url = if failed_only {
+            format!(
+                "https://api.github.com/repos/{}/actions/runs/{}/rerun-failed-jobs",
+                repo_name, run_id
+            )
+        } else {
+            format!(
+                "https://api.github.com/repos/{}/actions/runs/{}/rerun",
+                repo_name, run_id
+            )
+        };
         
-        runs.workflow_runs
-            .first()
-            .map(|run| run.id)
-            .ok_or_else(|| anyhow!("No workflow runs found"))
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+            
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to re-run workflow: {}", error_text));
+        }
+        
+        Ok(())
     }
     
     pub async fn get_run_status(&self, run_id: u64, repo_name: &str) -> Result<String> {
@@ -424,9 +949,8 @@ impl GitHubClient {
         let response = self
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
@@ -441,6 +965,7 @@ impl GitHubClient {
                 Some("success") => Ok("completed".to_string()),
                 Some("failure") => Ok("failure".to_string()),
                 Some("cancelled") => Ok("cancelled".to_string()),
+                Some("timed_out") => Ok("timed_out".to_string()),
                 _ => Ok("completed".to_string()),
             }
         } else {
@@ -448,104 +973,352 @@ impl GitHubClient {
         }
     }
     
-    pub async fn get_job_steps(&self, run_id: u64, repo_name: &str) -> Result<Vec<serde_json::Value>> {
+    /// 列出 run 的所有 job（分页，一次同步的镜像可能超过一页）
+    pub async fn get_run_jobs(&self, run_id: u64, repo_name: &str) -> Result<Vec<serde_json::Value>> {
         let jobs_url = format!(
             "https://api.github.com/repos/{}/actions/runs/{}/jobs",
             repo_name, run_id
         );
+        let mut jobs = Vec::new();
+        let mut page = 1;
+        
+        loop {
+            let response = self
+                .client
+                .get(format!("{}?per_page=100&page={}", jobs_url, page))
+                .header("Authorization", self.authorization().await?)
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .await?;
+                
+            if !response.status().is_success() {
+                return Err(anyhow!("Failed to list jobs of run {}: {}", run_id, response.status()));
+            }
+            
+            let body: serde_json::Value = response.json().await?;
+            let batch = body["jobs"].as_array().cloned().unwrap_or_default();
+            let done = batch.len() < 100;
+            jobs.extend(batch);
+            if done {
+                return Ok(jobs);
+            }
+            page += 1;
+        }
+    }
+    
+    pub async fn get_run_logs(&self, run_id: u64, repo_name: &str) -> Result<String> {
+        let mut all_logs = String::new();
+        
+        for job in self.get_run_jobs(run_id, repo_name).await? {
+            if let Some(job_id) = job["id"].as_u64() {
+                if let Ok(job_logs) = self.get_job_logs(job_id, repo_name).await {
+                    all_logs.push_str(&job_logs);
+                    all_logs.push('\n');
+                }
+            }
+        }
+        
+        Ok(all_logs)
+    }
+    
+    /// 获取单个 job 的完整日志（job 运行中时 GitHub 可能返回空内容）
+    pub async fn get_job_logs(&self, job_id: u64, repo_name: &str) -> Result<String> {
+        let url = format!(
+            "https://api.github.com/repos/{}/actions/jobs/{}/logs",
+            repo_name, job_id
+        );
         
         let response = self
             .client
-            .get(&jobs_url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
-        if !response.status().is_success() {
-            return Ok(vec![]);
+        if response.status().is_success() {
+            Ok(response.text().await?)
+        } else {
+            Ok(String::new())
         }
+    }
+    
+    pub async fn list_run_artifacts(&self, run_id: u64, repo_name: &str) -> Result<Vec<Artifact>> {
+        let url = format!(
+            "https://api.github.com/repos/{}/actions/runs/{}/artifacts?per_page=100",
+            repo_name, run_id
+        );
         
-        let jobs: serde_json::Value = response.json().await?;
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to list artifacts: {}", response.status()));
+        }
         
-        if let Some(jobs_array) = jobs["jobs"].as_array() {
-            if let Some(job) = jobs_array.first() {
-                if let Some(steps) = job["steps"].as_array() {
-                    return Ok(steps.clone());
-                }
+        let artifacts: ArtifactsResponse = response.json().await?;
+        Ok(artifacts.artifacts)
+    }
+    
+    /// 下载并解析 run 上传的同步结果；旧版工作流没有该 artifact 时返回空列表
+    pub async fn get_sync_results(&self, run_id: u64, repo_name: &str) -> Result<Vec<SyncResult>> {
+        let mut artifacts: Vec<Artifact> = self
+            .list_run_artifacts(run_id, repo_name)
+            .await?
+            .into_iter()
+            .filter(|artifact| artifact.name.starts_with("sync-result") && !artifact.expired)
+            .collect();
+        
+        // 重新运行会产生同名 artifact，只保留最新的一份
+        artifacts.sort_by_key(|artifact| std::cmp::Reverse(artifact.created_at));
+        let mut seen = std::collections::HashSet::new();
+        artifacts.retain(|artifact| seen.insert(artifact.name.clone()));
+        
+        let mut results = Vec::new();
+        for artifact in &artifacts {
+            let response = self
+                .client
+                .get(&artifact.archive_download_url)
+                .header("Authorization", self.authorization().await?)
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .await?;
+                
+            if !response.status().is_success() {
+                return Err(anyhow!("Failed to download artifact {}: {}", artifact.name, response.status()));
             }
+            
+            let bytes = response.bytes().await?;
+            let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+            let file = archive.by_name("sync-result.json")?;
+            results.push(serde_json::from_reader(file)?);
         }
         
-        Ok(vec![])
+        Ok(results)
     }
     
-    pub async fn get_run_logs(&self, run_id: u64, repo_name: &str) -> Result<String> {
-        // First get the jobs for this run
-        let jobs_url = format!(
-            "https://api.github.com/repos/{}/actions/runs/{}/jobs",
-            repo_name, run_id
+    /// 获取仓库 Actions secrets 的公钥，返回 (key_id, base64 公钥)
+    async fn get_actions_public_key(&self, repo_name: &str) -> Result<(String, String)> {
+        let url = format!(
+            "https://api.github.com/repos/{}/actions/secrets/public-key",
+            repo_name
         );
         
         let response = self
             .client
-            .get(&jobs_url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .get(&url)
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
         if !response.status().is_success() {
-            return Ok(String::new());
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to get repository public key: {}", error_text));
         }
         
-        let jobs: serde_json::Value = response.json().await?;
-        let mut all_logs = String::new();
+        let key: serde_json::Value = response.json().await?;
+        match (key["key_id"].as_str(), key["key"].as_str()) {
+            (Some(key_id), Some(key)) => Ok((key_id.to_string(), key.to_string())),
+            _ => Err(anyhow!("Invalid public key response")),
+        }
+    }
+    
+    /// 用仓库公钥（sealed box）加密后上传 Actions secret
+    pub async fn set_actions_secret(&self, repo_name: &str, name: &str, value: &str) -> Result<()> {
+        let (key_id, key) = self.get_actions_public_key(repo_name).await?;
         
-        if let Some(jobs_array) = jobs["jobs"].as_array() {
-            for job in jobs_array {
-                if let Some(job_id) = job["id"].as_u64() {
-                    if let Ok(job_logs) = self.get_job_logs(job_id, repo_name).await {
-                        all_logs.push_str(&job_logs);
-                        all_logs.push('\n');
-                    }
-                }
-            }
+        let key_bytes = base64::engine::general_purpose::STANDARD.decode(key)?;
+        let public_key = crypto_box::PublicKey::from_slice(&key_bytes)
+            .map_err(|_| anyhow!("Invalid repository public key"))?;
+        let sealed = public_key
+            .seal(&mut crypto_box::aead::OsRng, value.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+        
+        let payload = json!({
+            "encrypted_value": base64::engine::general_purpose::STANDARD.encode(sealed),
+            "key_id": key_id
+        });
+        
+        let url = format!(
+            "https://api.github.com/repos/{}/actions/secrets/{}",
+            repo_name, name
+        );
+        
+        let response = self
+            .client
+            .put(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .json(&payload)
+            .send()
+            .await?;
+            
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to set secret {}: {}", name, error_text));
         }
         
-        Ok(all_logs)
+        Ok(())
     }
     
-    async fn get_job_logs(&self, job_id: u64, repo_name: &str) -> Result<String> {
+    /// 列出仓库 Actions secret 的名称和更新时间
+    pub async fn list_actions_secrets(&self, repo_name: &str) -> Result<Vec<(String, Option<DateTime<Utc>>)>> {
         let url = format!(
-            "https://api.github.com/repos/{}/actions/jobs/{}/logs",
-            repo_name, job_id
+            "https://api.github.com/repos/{}/actions/secrets?per_page=100",
+            repo_name
         );
         
         let response = self
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
             
-        if response.status().is_success() {
-            Ok(response.text().await?)
-        } else {
-            Ok(String::new())
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to list secrets: {}", error_text));
         }
+        
+        let body: serde_json::Value = response.json().await?;
+        Ok(body["secrets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|secret| {
+                let name = secret["name"].as_str()?.to_string();
+                let updated_at = secret["updated_at"]
+                    .as_str()
+                    .and_then(|t| t.parse::<DateTime<Utc>>().ok());
+                Some((name, updated_at))
+            })
+            .collect())
     }
     
-    pub async fn delete_package(&self, package_name: &str) -> Result<()> {
+    pub async fn delete_actions_secret(&self, repo_name: &str, name: &str) -> Result<bool> {
+        let url = format!(
+            "https://api.github.com/repos/{}/actions/secrets/{}",
+            repo_name, name
+        );
+        
+        let response = self
+            .client
+            .delete(&url)
+            .header("Authorization", self.authorization().await?)
+            .header("Accept", "application/vnd.github.v3+json")
+            .send()
+            .await?;
+            
+        if response.status().as_u16() == 404 {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Failed to delete secret {}: {}", name, error_text));
+        }
+        
+        Ok(true)
+    }
+    
+    /// container package 的 API 根路径
+    fn packages_url(&self) -> Result<String> {
+        let username = self.username.as_ref().ok_or_else(|| anyhow!("Username not set"))?;
+        Ok(format!(
+            "https://api.github.com/{}/{}/packages/container",
+            if self.owner_is_org { "orgs" } else { "users" },
+            username
+        ))
+    }
+    
+    /// 列出所有 container package（分页）
+    pub async fn list_packages(&self) -> Result<Vec<Package>> {
         let username = self.username.as_ref().ok_or_else(|| anyhow!("Username not set"))?;
+        let mut packages = Vec::new();
+        let mut page = 1;
+        
+        loop {
+            let url = format!(
+                "https://api.github.com/users/{}/packages?package_type=container&per_page=100&page={}",
+                username, page
+            );
+            
+            let response = self
+                .client
+                .get(&url)
+                .header("Authorization", self.authorization().await?)
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .await?;
+                
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(anyhow!("Failed to list packages: {} {}", status, error_text));
+            }
+            
+            let batch: Vec<Package> = response.json().await?;
+            let done = batch.len() < 100;
+            packages.extend(batch);
+            if done {
+                return Ok(packages);
+            }
+            page += 1;
+        }
+    }
+    
+    /// 列出 package 的所有版本（分页，最新的在前）；package 不存在时返回空列表
+    pub async fn list_package_versions(&self, package_name: &str) -> Result<Vec<PackageVersion>> {
+        let versions_url = format!(
+            "{}/{}/versions",
+            self.packages_url()?,
+            encode_package_name(package_name)
+        );
+        let mut versions = Vec::new();
+        let mut page = 1;
         
+        loop {
+            let response = self
+                .client
+                .get(format!("{}?per_page=100&page={}", versions_url, page))
+                .header("Authorization", self.authorization().await?)
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .await?;
+                
+            if response.status().as_u16() == 404 {
+                return Ok(versions);
+            }
+            if !response.status().is_success() {
+                return Err(anyhow!(
+                    "Failed to list versions of {}: {}",
+                    package_name,
+                    response.status()
+                ));
+            }
+            
+            let batch: Vec<PackageVersion> = response.json().await?;
+            let done = batch.len() < 100;
+            versions.extend(batch);
+            if done {
+                return Ok(versions);
+            }
+            page += 1;
+        }
+    }
+    
+    pub async fn delete_package(&self, package_name: &str) -> Result<()> {
         // 直接删除整个 package
         let delete_url = format!(
-            "https://api.github.com/users/{}/packages/container/{}",
-            username, package_name
+            "{}/{}",
+            self.packages_url()?,
+            encode_package_name(package_name)
         );
         
         println!("{} 正在删除 {}...", "🗑️".yellow(), package_name);
@@ -553,9 +1326,8 @@ impl GitHubClient {
         let response = self
             .client
             .delete(&delete_url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
         
@@ -564,135 +1336,148 @@ impl GitHubClient {
         } else if response.status().as_u16() == 404 {
             println!("{} '{}' 不存在", "⚠️".yellow(), package_name);
         } else {
+            let status = response.status();
             let err = response.text().await.unwrap_or_default();
-            println!("{} 删除失败: {}", "✗".red(), err);
+            return Err(anyhow!("Failed to delete package {}: {} {}", package_name, status, err));
         }
         
         Ok(())
     }
     
     pub async fn delete_package_version(&self, package_name: &str, tag: &str) -> Result<()> {
-        let username = self.username.as_ref().ok_or_else(|| anyhow!("Username not set"))?;
-        
         // 获取所有版本，找到匹配 tag 的版本
-        let versions_url = format!(
-            "https://api.github.com/users/{}/packages/container/{}/versions",
-            username, package_name
-        );
-        
-        let response = self
-            .client
-            .get(&versions_url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
-            .send()
-            .await?;
+        let versions = self.list_package_versions(package_name).await?;
         
-        if !response.status().is_success() {
-            if response.status().as_u16() == 404 {
-                return Ok(()); // package 不存在
+        if let Some(version) = versions.iter().find(|v| v.has_tag(tag)) {
+            // 如果只有一个版本，删除整个 package
+            if versions.len() == 1 {
+                return self.delete_package(package_name).await;
             }
-            return Ok(());
-        }
-        
-        let versions: Vec<serde_json::Value> = response.json().await?;
-        
-        // 找到匹配 tag 的版本
-        for version in &versions {
-            if let Some(tags) = version["metadata"]["container"]["tags"].as_array() {
-                let has_tag = tags.iter().any(|t| t.as_str() == Some(tag));
-                if has_tag {
-                    if let Some(version_id) = version["id"].as_u64() {
-                        // 如果只有一个版本，删除整个 package
-                        if versions.len() == 1 {
-                            return self.delete_package(package_name).await;
-                        }
-                        
-                        // 否则只删除这个版本
-                        let delete_url = format!(
-                            "https://api.github.com/users/{}/packages/container/{}/versions/{}",
-                            username, package_name, version_id
-                        );
-                        
-                        println!("{} 正在删除 {}:{}...", "🗑️".yellow(), package_name, tag);
-                        
-                        let del_response = self
-                            .client
-                            .delete(&delete_url)
-                            .header("Authorization", format!("Bearer {}", self.token))
-                            .header("Accept", "application/vnd.github.v3+json")
-                            .header("User-Agent", "docker-sync-cli")
-                            .send()
-                            .await?;
-                        
-                        if del_response.status().is_success() || del_response.status().as_u16() == 204 {
-                            println!("{} 已删除 {}:{}", "✓".green(), package_name, tag);
-                        } else {
-                            let err = del_response.text().await.unwrap_or_default();
-                            println!("{} 删除失败: {}", "✗".red(), err);
-                        }
-                        return Ok(());
-                    }
-                }
+            
+            // 否则只删除这个版本
+            let delete_url = format!(
+                "{}/{}/versions/{}",
+                self.packages_url()?,
+                encode_package_name(package_name),
+                version.id
+            );
+            
+            println!("{} 正在删除 {}:{}...", "🗑️".yellow(), package_name, tag);
+            
+            let del_response = self
+                .client
+                .delete(&delete_url)
+                .header("Authorization", self.authorization().await?)
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .await?;
+            
+            if del_response.status().is_success() || del_response.status().as_u16() == 204 {
+                println!("{} 已删除 {}:{}", "✓".green(), package_name, tag);
+            } else {
+                let status = del_response.status();
+                let err = del_response.text().await.unwrap_or_default();
+                return Err(anyhow!("Failed to delete {}:{}: {} {}", package_name, tag, status, err));
             }
         }
         
         Ok(())
     }
     
-    pub async fn package_version_exists(&self, package_name: &str, tag: &str) -> Result<bool> {
-        let username = self.username.as_ref().ok_or_else(|| anyhow!("Username not set"))?;
-        
-        let versions_url = format!(
-            "https://api.github.com/users/{}/packages/container/{}/versions",
-            username, package_name
+    /// 按版本 ID 删除；GitHub 不允许删除 package 的最后一个版本，调用方需改为删除整个 package
+    pub async fn delete_package_version_by_id(&self, package_name: &str, version_id: u64) -> Result<()> {
+        let delete_url = format!(
+            "{}/{}/versions/{}",
+            self.packages_url()?,
+            encode_package_name(package_name),
+            version_id
         );
         
         let response = self
             .client
-            .get(&versions_url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .delete(&delete_url)
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
         
         if !response.status().is_success() {
-            return Ok(false);
+            let status = response.status();
+            let err = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to delete version {}: {} {}", version_id, status, err));
         }
         
-        let versions: Vec<serde_json::Value> = response.json().await?;
-        
-        for version in &versions {
-            if let Some(tags) = version["metadata"]["container"]["tags"].as_array() {
-                if tags.iter().any(|t| t.as_str() == Some(tag)) {
-                    return Ok(true);
-                }
-            }
+        Ok(())
+    }
+    
+    pub async fn package_version_exists(&self, package_name: &str, tag: &str) -> Result<bool> {
+        match self.list_package_versions(package_name).await {
+            Ok(versions) => Ok(versions.iter().any(|v| v.has_tag(tag))),
+            Err(_) => Ok(false),
         }
-        
-        Ok(false)
     }
     
     #[allow(dead_code)]
     pub async fn package_exists(&self, package_name: &str) -> Result<bool> {
-        let username = self.username.as_ref().ok_or_else(|| anyhow!("Username not set"))?;
-        
         let url = format!(
-            "https://api.github.com/users/{}/packages/container/{}",
-            username, package_name
+            "{}/{}",
+            self.packages_url()?,
+            encode_package_name(package_name)
         );
         
         let response = self
             .client
             .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Authorization", self.authorization().await?)
             .header("Accept", "application/vnd.github.v3+json")
-            .header("User-Agent", "docker-sync-cli")
             .send()
             .await?;
         
         Ok(response.status().is_success())
     }
-}
\ No newline at end of file
+}
+
+/// runs-on 的 JSON 表示：单个标签为字符串，多个标签为数组
+fn runs_on_json(labels: &[String]) -> String {
+    match labels {
+        [] => json!("ubuntu-latest").to_string(),
+        [label] => json!(label).to_string(),
+        labels => json!(labels).to_string(),
+    }
+}
+
+/// package 名称可能包含 '/'（如 bitnami/redis），在 URL 路径中需要编码
+fn encode_package_name(name: &str) -> String {
+    url::form_urlencoded::byte_serialize(name.as_bytes()).collect()
+}
+
+/// 解析 "2024-03-01 00:00:00 UTC" 形式的令牌过期时间
+fn parse_token_expiration(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M:%S %z")
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            let naive = value.trim().trim_end_matches("UTC").trim();
+            chrono::NaiveDateTime::parse_from_str(naive, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|time| time.and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_token_expiration_reads_github_header_formats() {
+        let expected = "2024-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parse_token_expiration("2024-03-01 00:00:00 UTC"), Some(expected));
+        assert_eq!(parse_token_expiration(" 2024-03-01 08:00:00 +0800 "), Some(expected));
+    }
+
+    #[test]
+    fn parse_token_expiration_rejects_other_values() {
+        assert_eq!(parse_token_expiration(""), None);
+        assert_eq!(parse_token_expiration("next week"), None);
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{anyhow, Result};
+use colored::*;
+use reqwest::{Certificate, Client, Proxy, Url};
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// 所有请求统一使用的 User-Agent
+pub const USER_AGENT: &str = concat!("docker-sync-cli/", env!("CARGO_PKG_VERSION"));
+
+/// per-host 规则中表示直连的值
+pub const DIRECT: &str = "direct";
+
+/// 为每个请求选择代理：per-host 规则 > NO_PROXY > 配置的代理 > HTTPS_PROXY/HTTP_PROXY/ALL_PROXY
+#[derive(Debug, Clone, Default)]
+struct ProxyRules {
+    /// 主机规则及对应的代理，None 表示直连
+    hosts: Vec<(String, Option<Url>)>,
+    no_proxy: Vec<String>,
+    proxy: Option<Url>,
+    https_proxy: Option<Url>,
+    http_proxy: Option<Url>,
+    /// 无法解析的代理地址，创建客户端时提示
+    errors: Vec<String>,
+}
+
+impl ProxyRules {
+    fn from_config(config: &Config) -> Self {
+        let mut errors = Vec::new();
+        let mut parse = |proxy: &str, source: &str| match Url::parse(proxy) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                errors.push(format!("{}: {} - {}", source, proxy, e));
+                None
+            }
+        };
+        let hosts = config
+            .proxy_rules
+            .iter()
+            .filter_map(|(pattern, proxy)| {
+                let proxy = if proxy == DIRECT {
+                    None
+                } else {
+                    Some(parse(proxy, &format!("proxy_rules.{}", pattern))?)
+                };
+                Some((pattern.to_lowercase(), proxy))
+            })
+            .collect();
+        let no_proxy = env_var(&["NO_PROXY", "no_proxy"])
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|entry| entry.trim().to_lowercase())
+                    .filter(|entry| !entry.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let all_proxy = env_var(&["ALL_PROXY", "all_proxy"]);
+
+        let proxy = config.proxy.as_deref().and_then(|proxy| parse(proxy, "proxy"));
+        let https_proxy = env_var(&["HTTPS_PROXY", "https_proxy"])
+            .or_else(|| all_proxy.clone())
+            .and_then(|proxy| parse(&proxy, "HTTPS_PROXY"));
+        let http_proxy = env_var(&["HTTP_PROXY", "http_proxy"])
+            .or(all_proxy)
+            .and_then(|proxy| parse(&proxy, "HTTP_PROXY"));
+
+        Self {
+            hosts,
+            no_proxy,
+            proxy,
+            https_proxy,
+            http_proxy,
+            errors,
+        }
+    }
+
+    /// 请求 url 使用的代理，None 表示直连
+    fn proxy_for(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?.to_lowercase();
+        // 多条规则匹配时使用最具体的一条，与规则的书写顺序无关
+        if let Some((_, proxy)) = self
+            .hosts
+            .iter()
+            .filter(|(pattern, _)| host_matches(pattern, &host))
+            .max_by_key(|(pattern, _)| specificity(pattern, &host))
+        {
+            return proxy.clone();
+        }
+        if self.no_proxy.iter().any(|pattern| host_matches(pattern, &host)) {
+            return None;
+        }
+        if self.proxy.is_some() {
+            return self.proxy.clone();
+        }
+        match url.scheme() {
+            "https" => self.https_proxy.clone(),
+            _ => self.http_proxy.clone(),
+        }
+    }
+}
+
+/// 按配置创建 HTTP 客户端：代理规则、连接/请求超时、额外的 CA 证书和统一的 User-Agent；
+/// 创建失败时返回错误，不回退到忽略这些配置的默认客户端
+pub fn client(config: &Config) -> Result<Client> {
+    build_client(config).map_err(|e| anyhow!("HTTP 客户端创建失败: {}", e))
+}
+
+/// 按配置访问 url 时使用的代理，None 表示直连
+pub fn proxy_for(config: &Config, url: &str) -> Option<Url> {
+    ProxyRules::from_config(config).proxy_for(&Url::parse(url).ok()?)
+}
+
+fn build_client(config: &Config) -> Result<Client> {
+    let rules = ProxyRules::from_config(config);
+    for error in &rules.errors {
+        println!("{} 代理配置错误 ({})", "⚠️".yellow(), error);
+        println!("{} 将使用直连方式", "📡".yellow());
+    }
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(config.connect_timeout))
+        .timeout(Duration::from_secs(config.request_timeout))
+        .proxy(Proxy::custom(move |url| rules.proxy_for(url)));
+
+    // 企业网络的 TLS 拦截证书；SSL_CERT_FILE 与 OpenSSL 的约定一致
+    let ca_bundles = config
+        .ca_bundles
+        .iter()
+        .cloned()
+        .chain(env_var(&["SSL_CERT_FILE"]));
+    for path in ca_bundles {
+        let certificates = load_certificates(&path).map_err(|e| anyhow!("无法加载 CA 证书 {}: {}", path, e))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+fn load_certificates(path: &str) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path)?;
+    let certificates = Certificate::from_pem_bundle(&pem)?;
+    if certificates.is_empty() {
+        return Err(anyhow!("文件中没有 PEM 格式的证书"));
+    }
+    Ok(certificates)
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// `*` 匹配所有主机，`example.com`、`.example.com` 和 `*.example.com` 匹配该域名及其子域名
+fn host_matches(pattern: &str, host: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    let domain = pattern_domain(pattern);
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// 匹配规则的具体程度：与主机完全相同的域名最高，其次是更长的域名后缀，`*` 最低
+fn specificity(pattern: &str, host: &str) -> usize {
+    if pattern == "*" {
+        return 0;
+    }
+    let domain = pattern_domain(pattern);
+    if domain == host {
+        usize::MAX
+    } else {
+        domain.len()
+    }
+}
+
+/// 规则中的域名部分，去掉 `*.`、`.` 前缀和端口（NO_PROXY 中可能带端口，端口不参与匹配）
+fn pattern_domain(pattern: &str) -> &str {
+    let pattern = match pattern.rsplit_once(':') {
+        Some((domain, port)) if !domain.contains(':') && port.chars().all(|c| c.is_ascii_digit()) => domain,
+        _ => pattern,
+    };
+    pattern.trim_start_matches("*.").trim_start_matches('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    #[test]
+    fn host_matches_domain_and_subdomains() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("example.com", "api.example.com"));
+        assert!(!host_matches("example.com", "badexample.com"));
+        assert!(!host_matches("api.example.com", "example.com"));
+    }
+
+    #[test]
+    fn host_matches_wildcard_and_leading_dot() {
+        assert!(host_matches("*", "ghcr.io"));
+        assert!(host_matches("*.example.com", "example.com"));
+        assert!(host_matches("*.example.com", "a.b.example.com"));
+        assert!(host_matches(".example.com", "api.example.com"));
+        assert!(!host_matches(".example.com", "example.org"));
+    }
+
+    #[test]
+    fn host_matches_ignores_port() {
+        assert!(host_matches("registry.local:5000", "registry.local"));
+        assert!(host_matches("localhost:8080", "localhost"));
+        assert!(!host_matches("registry.local:5000", "other.local"));
+    }
+
+    #[test]
+    fn proxy_for_prefers_host_rules_then_no_proxy() {
+        let rules = ProxyRules {
+            hosts: vec![
+                ("ghcr.io".to_string(), Some(url("http://127.0.0.1:7890"))),
+                ("*.corp.example".to_string(), None),
+            ],
+            no_proxy: vec!["github.com".to_string()],
+            proxy: Some(url("socks5://127.0.0.1:1080")),
+            https_proxy: Some(url("http://env-proxy:3128")),
+            ..ProxyRules::default()
+        };
+        assert_eq!(rules.proxy_for(&url("https://ghcr.io/v2/")), Some(url("http://127.0.0.1:7890")));
+        assert_eq!(rules.proxy_for(&url("https://registry.corp.example/v2/")), None);
+        assert_eq!(rules.proxy_for(&url("https://api.github.com/user")), None);
+        assert_eq!(rules.proxy_for(&url("https://registry-1.docker.io/v2/")), Some(url("socks5://127.0.0.1:1080")));
+    }
+
+    #[test]
+    fn proxy_for_uses_most_specific_rule() {
+        // BTreeMap 中 * 排在所有主机名之前，书写顺序也不影响结果
+        let rules = ProxyRules {
+            hosts: vec![
+                ("*".to_string(), Some(url("socks5://127.0.0.1:1080"))),
+                ("*.corp.example".to_string(), None),
+                ("build.corp.example".to_string(), Some(url("http://build-proxy:3128"))),
+            ],
+            ..ProxyRules::default()
+        };
+        assert_eq!(rules.proxy_for(&url("https://registry.corp.example/")), None);
+        assert_eq!(rules.proxy_for(&url("https://build.corp.example/")), Some(url("http://build-proxy:3128")));
+        assert_eq!(rules.proxy_for(&url("https://ci.build.corp.example/")), Some(url("http://build-proxy:3128")));
+        assert_eq!(rules.proxy_for(&url("https://ghcr.io/")), Some(url("socks5://127.0.0.1:1080")));
+    }
+
+    #[test]
+    fn proxy_for_falls_back_to_environment_by_scheme() {
+        let rules = ProxyRules {
+            https_proxy: Some(url("http://https-proxy:3128")),
+            http_proxy: Some(url("http://http-proxy:3128")),
+            ..ProxyRules::default()
+        };
+        assert_eq!(rules.proxy_for(&url("https://ghcr.io/")), Some(url("http://https-proxy:3128")));
+        assert_eq!(rules.proxy_for(&url("http://registry.local:5000/")), Some(url("http://http-proxy:3128")));
+    }
+
+    #[test]
+    fn client_fails_when_ca_bundle_is_missing() {
+        let mut config = Config::default();
+        config.ca_bundles = vec!["/nonexistent/docker-sync-ca.pem".to_string()];
+        let error = client(&config).unwrap_err();
+        assert!(error.to_string().contains("/nonexistent/docker-sync-ca.pem"));
+    }
+}
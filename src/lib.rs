@@ -0,0 +1,5 @@
+//! docker-sync 和凭据助手 docker-credential-docker-sync 共用的配置、设置项和令牌存储
+
+pub mod config;
+pub mod credentials;
+pub mod settings;
@@ -1,19 +1,52 @@
-use anyhow::Result;
-use reqwest::Client;
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Method, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::http;
+
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+
+/// 镜像引用的一个 blob（manifest、config 或 layer）
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub digest: String,
+    pub size: u64,
+}
 
-#[allow(dead_code)]
 pub struct RegistryClient {
     client: Client,
+    /// 用于换取 Bearer token 的用户名和密码
+    credentials: Mutex<Option<(String, String)>>,
+    /// scope -> Bearer token
+    tokens: Mutex<HashMap<String, String>>,
 }
 
-#[allow(dead_code)]
 impl RegistryClient {
-    pub fn new() -> Self {
-        Self {
-            client: Client::new(),
-        }
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            client: http::client(config)?,
+            credentials: Mutex::new(None),
+            tokens: Mutex::new(HashMap::new()),
+        })
     }
     
+    /// 设置换取 token 时使用的凭据（GHCR 使用 GitHub 用户名和 token）；
+    /// GitHub App 安装令牌一小时后过期，长时间运行时需在每次检查前重新设置
+    pub fn set_credentials(&self, username: &str, password: &str) {
+        let credentials = Some((username.to_string(), password.to_string()));
+        let mut current = self.credentials.lock().unwrap();
+        if *current != credentials {
+            *current = credentials;
+            self.tokens.lock().unwrap().clear();
+        }
+    }
+
+    #[allow(dead_code)]
     pub async fn image_exists(&self, image: &str) -> Result<bool> {
         // Parse image name and tag
         let (registry_image, tag) = if let Some(pos) = image.rfind(':') {
@@ -53,4 +86,284 @@ impl RegistryClient {
             Err(_) => Ok(false), // 网络错误时假设镜像不存在
         }
     }
-}
\ No newline at end of file
+
+    /// 获取 manifest 原始内容及其摘要
+    pub async fn get_manifest(
+        &self,
+        registry: &str,
+        repository: &str,
+        reference: &str,
+    ) -> Result<(String, Vec<u8>)> {
+        let url = format!("https://{}/v2/{}/manifests/{}", registry, repository, reference);
+        let response = self.send(Method::GET, &url, repository).await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to get manifest {}/{}:{}: {}",
+                registry,
+                repository,
+                reference,
+                response.status()
+            ));
+        }
+
+        let digest = response
+            .headers()
+            .get("docker-content-digest")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response.bytes().await?.to_vec();
+        let digest = digest.unwrap_or_else(|| reference.to_string());
+        Ok((digest, body))
+    }
+
+    /// 列出镜像引用的全部 blob：manifest 本身、config 和 layer；多平台镜像会展开每个平台
+    pub async fn image_blobs(&self, registry: &str, repository: &str, reference: &str) -> Result<Vec<Blob>> {
+        let (digest, body) = self.get_manifest(registry, repository, reference).await?;
+        let manifest: serde_json::Value = serde_json::from_slice(&body)?;
+
+        let mut blobs = vec![Blob {
+            digest,
+            size: body.len() as u64,
+        }];
+
+        if let Some(children) = manifest["manifests"].as_array() {
+            for child in children {
+                if let Some(child_digest) = child["digest"].as_str() {
+                    let child_blobs =
+                        Box::pin(self.image_blobs(registry, repository, child_digest)).await?;
+                    blobs.extend(child_blobs);
+                }
+            }
+            return Ok(blobs);
+        }
+
+        let config = std::iter::once(&manifest["config"]);
+        let layers = manifest["layers"].as_array().into_iter().flatten();
+        for descriptor in config.chain(layers) {
+            if let (Some(digest), Some(size)) = (descriptor["digest"].as_str(), descriptor["size"].as_u64()) {
+                blobs.push(Blob {
+                    digest: digest.to_string(),
+                    size,
+                });
+            }
+        }
+
+        Ok(blobs)
+    }
+
+    /// 用 HEAD 请求获取 manifest 摘要，不计入 Docker Hub 的拉取次数；镜像不存在时返回 None
+    pub async fn manifest_digest(&self, registry: &str, repository: &str, reference: &str) -> Result<Option<String>> {
+        let url = format!("https://{}/v2/{}/manifests/{}", registry, repository, reference);
+        let response = self.send(Method::HEAD, &url, repository).await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to check manifest {}/{}:{}: {}",
+                registry,
+                repository,
+                reference,
+                response.status()
+            ));
+        }
+
+        Ok(response
+            .headers()
+            .get("docker-content-digest")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string))
+    }
+
+    /// 获取指定平台镜像的 config 摘要（即镜像 ID），多平台镜像会先按平台选出对应 manifest；镜像不存在时返回 None
+    ///
+    /// docker pull/push 会改变 manifest 摘要，但 config 摘要保持不变，适合比较源镜像和同步后的镜像
+    pub async fn config_digest(
+        &self,
+        registry: &str,
+        repository: &str,
+        reference: &str,
+        os: &str,
+        architecture: &str,
+    ) -> Result<Option<String>> {
+        let url = format!("https://{}/v2/{}/manifests/{}", registry, repository, reference);
+        let response = self.send(Method::GET, &url, repository).await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to get manifest {}/{}:{}: {}",
+                registry,
+                repository,
+                reference,
+                response.status()
+            ));
+        }
+        let manifest: serde_json::Value = response.json().await?;
+
+        if let Some(children) = manifest["manifests"].as_array() {
+            let child = children
+                .iter()
+                .find(|child| {
+                    child["platform"]["os"].as_str() == Some(os)
+                        && child["platform"]["architecture"].as_str() == Some(architecture)
+                })
+                .and_then(|child| child["digest"].as_str())
+                .ok_or_else(|| {
+                    anyhow!("{}/{}:{} has no {}/{} image", registry, repository, reference, os, architecture)
+                })?;
+            return Box::pin(self.config_digest(registry, repository, child, os, architecture)).await;
+        }
+
+        Ok(manifest["config"]["digest"].as_str().map(str::to_string))
+    }
+
+    /// 发送请求，遇到 401 时按 WWW-Authenticate 换取 Bearer token 后重试
+    async fn send(&self, method: Method, url: &str, repository: &str) -> Result<Response> {
+        let scope = format!("repository:{}:pull", repository);
+        let cached = self.tokens.lock().unwrap().get(&scope).cloned();
+
+        let mut request = self.client.request(method.clone(), url).header("Accept", MANIFEST_ACCEPT);
+        if let Some(token) = &cached {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let challenge = response
+            .headers()
+            .get("www-authenticate")
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| anyhow!("Registry returned 401 without an authentication challenge"))?
+            .to_string();
+        let token = self.fetch_token(&challenge, &scope).await?;
+        self.tokens.lock().unwrap().insert(scope, token.clone());
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("Accept", MANIFEST_ACCEPT)
+            .bearer_auth(token)
+            .send()
+            .await?)
+    }
+
+    async fn fetch_token(&self, challenge: &str, scope: &str) -> Result<String> {
+        let params = parse_challenge(challenge);
+        let realm = params
+            .get("realm")
+            .ok_or_else(|| anyhow!("Unsupported authentication challenge: {}", challenge))?;
+
+        let mut query = vec![("scope", params.get("scope").map_or(scope, |s| s.as_str()))];
+        if let Some(service) = params.get("service") {
+            query.push(("service", service));
+        }
+
+        let mut request = self.client.get(realm).query(&query);
+        let credentials = self.credentials.lock().unwrap().clone();
+        if let Some((username, password)) = &credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to get registry token: {}", response.status()));
+        }
+
+        let body: serde_json::Value = response.json().await?;
+        body["token"]
+            .as_str()
+            .or_else(|| body["access_token"].as_str())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Registry token response has no token"))
+    }
+}
+
+/// 将镜像引用拆分为 (registry, repository, tag)，按 Docker 规则补全 Docker Hub 地址和 library/ 前缀
+pub fn parse_image_reference(image: &str) -> (String, String, String) {
+    let (name, tag) = match image.rsplit_once(':') {
+        Some((name, tag)) if !tag.contains('/') => (name, tag),
+        _ => (image, "latest"),
+    };
+
+    let (registry, repository) = match name.split_once('/') {
+        Some((host, rest)) if host.contains('.') || host.contains(':') || host == "localhost" => {
+            (host.to_string(), rest.to_string())
+        }
+        _ => ("docker.io".to_string(), name.to_string()),
+    };
+
+    if registry == "docker.io" || registry == "index.docker.io" {
+        let repository = if repository.contains('/') {
+            repository
+        } else {
+            format!("library/{}", repository)
+        };
+        return ("registry-1.docker.io".to_string(), repository, tag.to_string());
+    }
+
+    (registry, repository, tag.to_string())
+}
+
+/// 解析 `Bearer realm="...",service="...",scope="..."`
+fn parse_challenge(challenge: &str) -> HashMap<String, String> {
+    let params = challenge
+        .strip_prefix("Bearer ")
+        .or_else(|| challenge.strip_prefix("bearer "))
+        .unwrap_or(challenge);
+
+    let mut result = HashMap::new();
+    let mut rest = params;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().trim_start_matches(',').trim().to_lowercase();
+        let value_part = &rest[eq + 1..];
+        let (value, remaining) = if let Some(quoted) = value_part.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match value_part.find(',') {
+                Some(end) => (&value_part[..end], &value_part[end..]),
+                None => (value_part, ""),
+            }
+        };
+        result.insert(key, value.to_string());
+        rest = remaining;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_challenge_reads_quoted_parameters() {
+        let params = parse_challenge(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:octocat/nginx:pull,push""#,
+        );
+        assert_eq!(params["realm"], "https://ghcr.io/token");
+        assert_eq!(params["service"], "ghcr.io");
+        assert_eq!(params["scope"], "repository:octocat/nginx:pull,push");
+    }
+
+    #[test]
+    fn parse_challenge_accepts_unquoted_values_and_spacing() {
+        let params = parse_challenge(r#"bearer Realm=https://auth.docker.io/token, service="registry.docker.io""#);
+        assert_eq!(params["realm"], "https://auth.docker.io/token");
+        assert_eq!(params["service"], "registry.docker.io");
+    }
+
+    #[test]
+    fn parse_challenge_without_parameters_is_empty() {
+        assert!(parse_challenge("Basic").is_empty());
+    }
+}
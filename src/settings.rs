@@ -0,0 +1,815 @@
+use anyhow::{anyhow, Result};
+use colored::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{validate_profile_name, Config, Profile};
+use crate::credentials::CredentialStore;
+
+/// 项目配置文件名，从当前目录向上查找
+pub const PROJECT_FILE: &str = ".docker-sync.toml";
+
+/// 项目配置可以设置的配置项（另有 images）；项目配置随仓库分发，不能修改代理、证书、镜像仓库等
+/// 决定令牌和流量去向的配置
+const PROJECT_KEYS: [&str; 3] = ["owner", "repo", "runs_on"];
+
+/// 可由命名 profile 覆盖的配置项
+const PROFILE_KEYS: [&str; 4] = ["owner", "repo", "pull_registry", "proxy"];
+
+/// 配置项的取值类型，决定 set 时的校验方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// 镜像仓库主机名，可带端口，例如 ghcr.io、registry.local:5000
+    Host,
+    /// 逗号分隔的镜像仓库主机名
+    HostList,
+    /// http://、https://、socks5:// 或 socks5h:// 代理地址
+    ProxyUrl,
+    /// 逗号分隔的 主机=代理地址 规则，代理地址可为 direct
+    ProxyRules,
+    /// 正整数秒数
+    Seconds,
+    /// 逗号分隔的文件路径
+    FileList,
+    /// GitHub 用户名或组织名
+    GitHubName,
+    /// GitHub 仓库名
+    RepoName,
+    /// 逗号分隔的 Runner 标签
+    Labels,
+    Text,
+    /// 只能取列出的值
+    Enum(&'static [&'static str]),
+    /// 通过专门的命令修改
+    ReadOnly(&'static str),
+}
+
+/// `config get/set/unset/list` 支持的配置项
+pub struct Setting {
+    pub key: &'static str,
+    pub kind: Kind,
+    /// 覆盖此项的环境变量
+    pub env: &'static str,
+    pub description: &'static str,
+    /// 读取 Config 中的值，未设置时返回 None
+    read: fn(&Config) -> Option<String>,
+    /// 写入已校验的值
+    write: fn(&mut Config, String) -> Result<()>,
+    /// 原样复制 Config 中对应的字段
+    copy: fn(&Config, &mut Config),
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "pull_registry",
+        kind: Kind::Host,
+        env: "DOCKER_SYNC_PULL_REGISTRY",
+        description: "拉取镜像使用的镜像源",
+        read: |config| Some(config.pull_registry.clone()),
+        write: |config, value| {
+            config.pull_registry = value;
+            Ok(())
+        },
+        copy: |from, to| to.pull_registry = from.pull_registry.clone(),
+    },
+    Setting {
+        key: "ghcr_registry",
+        kind: Kind::Host,
+        env: "DOCKER_SYNC_GHCR_REGISTRY",
+        description: "同步的目标仓库",
+        read: |config| Some(config.ghcr_registry.clone()),
+        write: |config, value| {
+            config.ghcr_registry = value;
+            Ok(())
+        },
+        copy: |from, to| to.ghcr_registry = from.ghcr_registry.clone(),
+    },
+    Setting {
+        key: "custom_registries",
+        kind: Kind::HostList,
+        env: "DOCKER_SYNC_CUSTOM_REGISTRIES",
+        description: "额外的镜像源（逗号分隔）",
+        read: |config| list(&config.custom_registries),
+        write: |config, value| {
+            config.custom_registries = split_list(&value);
+            Ok(())
+        },
+        copy: |from, to| to.custom_registries = from.custom_registries.clone(),
+    },
+    Setting {
+        key: "helper_registries",
+        kind: Kind::HostList,
+        env: "DOCKER_SYNC_HELPER_REGISTRIES",
+        description: "凭据助手额外提供 GitHub 令牌的镜像源（逗号分隔，默认只提供给 ghcr_registry）",
+        read: |config| list(&config.helper_registries),
+        write: |config, value| {
+            config.helper_registries = split_list(&value);
+            Ok(())
+        },
+        copy: |from, to| to.helper_registries = from.helper_registries.clone(),
+    },
+    Setting {
+        key: "proxy",
+        kind: Kind::ProxyUrl,
+        env: "DOCKER_SYNC_PROXY",
+        description: "访问 GitHub 使用的代理",
+        read: |config| config.proxy.clone(),
+        write: |config, value| {
+            config.proxy = Some(value);
+            Ok(())
+        },
+        copy: |from, to| to.proxy = from.proxy.clone(),
+    },
+    Setting {
+        key: "proxy_rules",
+        kind: Kind::ProxyRules,
+        env: "DOCKER_SYNC_PROXY_RULES",
+        description: "按主机选择代理，例如 *.corp.example=direct,ghcr.io=http://127.0.0.1:7890",
+        read: |config| format_proxy_rules(&config.proxy_rules),
+        write: |config, value| {
+            config.proxy_rules = parse_proxy_rules(&value)?;
+            Ok(())
+        },
+        copy: |from, to| to.proxy_rules = from.proxy_rules.clone(),
+    },
+    Setting {
+        key: "connect_timeout",
+        kind: Kind::Seconds,
+        env: "DOCKER_SYNC_CONNECT_TIMEOUT",
+        description: "建立连接的超时时间（秒）",
+        read: |config| Some(config.connect_timeout.to_string()),
+        write: |config, value| {
+            config.connect_timeout = value.parse()?;
+            Ok(())
+        },
+        copy: |from, to| to.connect_timeout = from.connect_timeout,
+    },
+    Setting {
+        key: "request_timeout",
+        kind: Kind::Seconds,
+        env: "DOCKER_SYNC_REQUEST_TIMEOUT",
+        description: "单个请求的超时时间（秒）",
+        read: |config| Some(config.request_timeout.to_string()),
+        write: |config, value| {
+            config.request_timeout = value.parse()?;
+            Ok(())
+        },
+        copy: |from, to| to.request_timeout = from.request_timeout,
+    },
+    Setting {
+        key: "ca_bundles",
+        kind: Kind::FileList,
+        env: "DOCKER_SYNC_CA_BUNDLES",
+        description: "额外信任的 CA 证书文件（PEM，逗号分隔）",
+        read: |config| list(&config.ca_bundles),
+        write: |config, value| {
+            config.ca_bundles = split_list(&value);
+            Ok(())
+        },
+        copy: |from, to| to.ca_bundles = from.ca_bundles.clone(),
+    },
+    Setting {
+        key: "owner",
+        kind: Kind::GitHubName,
+        env: "DOCKER_SYNC_OWNER",
+        description: "同步到的 GitHub 账号或组织，为空时使用令牌所属用户",
+        read: |config| config.owner.clone(),
+        write: |config, value| {
+            config.owner = Some(value);
+            Ok(())
+        },
+        copy: |from, to| to.owner = from.owner.clone(),
+    },
+    Setting {
+        key: "repo",
+        kind: Kind::RepoName,
+        env: "DOCKER_SYNC_REPO",
+        description: "同步仓库名称，为空时使用 docker-sync",
+        read: |config| config.repo.clone(),
+        write: |config, value| {
+            config.repo = Some(value);
+            Ok(())
+        },
+        copy: |from, to| to.repo = from.repo.clone(),
+    },
+    Setting {
+        key: "runs_on",
+        kind: Kind::Labels,
+        env: "DOCKER_SYNC_RUNS_ON",
+        description: "同步工作流的 Runner 标签（逗号分隔）",
+        read: |config| list(&config.runs_on),
+        write: |config, value| {
+            config.runs_on = split_list(&value);
+            Ok(())
+        },
+        copy: |from, to| to.runs_on = from.runs_on.clone(),
+    },
+    Setting {
+        key: "oauth_client_id",
+        kind: Kind::Text,
+        env: "DOCKER_SYNC_CLIENT_ID",
+        description: "auth login 使用的 OAuth App client ID",
+        read: |config| config.oauth_client_id.clone(),
+        write: |config, value| {
+            config.oauth_client_id = Some(value);
+            Ok(())
+        },
+        copy: |from, to| to.oauth_client_id = from.oauth_client_id.clone(),
+    },
+    Setting {
+        key: "credential_store",
+        kind: Kind::Enum(&["file", "encrypted"]),
+        env: "DOCKER_SYNC_CREDENTIAL_STORE",
+        description: "GitHub 令牌的存储方式（helper 需使用 config set-credential-store）",
+        read: |config| Some(config.credential_store.name().to_string()),
+        write: |config, value| {
+            config.credential_store = match value.as_str() {
+                "encrypted" => CredentialStore::Encrypted,
+                _ => CredentialStore::File,
+            };
+            Ok(())
+        },
+        copy: |from, to| to.credential_store = from.credential_store.clone(),
+    },
+    Setting {
+        key: "current_profile",
+        kind: Kind::ReadOnly("docker-sync config use <profile>"),
+        env: "DOCKER_SYNC_PROFILE",
+        description: "默认使用的 profile",
+        read: |config| config.current_profile.clone(),
+        write: |config, value| {
+            config.current_profile = Some(value);
+            Ok(())
+        },
+        copy: |from, to| to.current_profile = from.current_profile.clone(),
+    },
+];
+
+pub fn find(key: &str) -> Result<&'static Setting> {
+    // 兼容 pull-registry 这样的写法
+    let key = key.replace('-', "_");
+    SETTINGS.iter().find(|setting| setting.key == key).ok_or_else(|| {
+        let keys: Vec<&str> = SETTINGS.iter().map(|setting| setting.key).collect();
+        anyhow!("未知的配置项 '{}'，可用: {}", key, keys.join(", "))
+    })
+}
+
+impl Setting {
+    /// 当前值，未设置时返回 None
+    pub fn get(&self, config: &Config) -> Option<String> {
+        (self.read)(config)
+    }
+
+    /// 校验并设置新值（credential_store 需要迁移令牌，由调用方处理）
+    pub fn set(&self, config: &mut Config, value: &str) -> Result<()> {
+        let value = self.validate(value)?;
+        (self.write)(config, value)
+    }
+
+    /// 恢复默认值
+    pub fn unset(&self, config: &mut Config) -> Result<()> {
+        if let Kind::ReadOnly(command) = self.kind {
+            return Err(anyhow!("{} 需通过 '{}' 修改", self.key, command));
+        }
+        (self.copy)(&Config::default(), config);
+        Ok(())
+    }
+
+    /// 按类型校验，返回去掉首尾空白后的值
+    pub fn validate(&self, value: &str) -> Result<String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(anyhow!("{} 不能为空，恢复默认值请使用 'docker-sync config unset {}'", self.key, self.key));
+        }
+        let result = match self.kind {
+            Kind::Host => validate_host(value),
+            Kind::HostList => value.split(',').try_for_each(|host| validate_host(host.trim())),
+            Kind::ProxyUrl => validate_proxy(value),
+            Kind::ProxyRules => parse_proxy_rules(value).map(|_| ()),
+            Kind::Seconds => validate_seconds(value),
+            Kind::FileList => value.split(',').try_for_each(|path| validate_file(path.trim())),
+            Kind::GitHubName => validate_github_name(value),
+            Kind::RepoName => validate_repo_name(value),
+            Kind::Labels if value.split(',').any(|label| label.trim().is_empty()) => {
+                Err(anyhow!("Runner 标签不能为空"))
+            }
+            Kind::Labels => Ok(()),
+            Kind::Text => Ok(()),
+            Kind::Enum(values) if !values.contains(&value) => {
+                Err(anyhow!("可选值: {}", values.join(", ")))
+            }
+            Kind::Enum(_) => Ok(()),
+            Kind::ReadOnly(command) => Err(anyhow!("需通过 '{}' 修改", command)),
+        };
+        result.map_err(|e| anyhow!("{} 的值 '{}' 无效: {}", self.key, value, e))?;
+        Ok(value.to_string())
+    }
+}
+
+impl Config {
+    /// 校验所有配置项（包括各 profile），用于 config edit 保存前
+    pub fn validate(&self) -> Result<()> {
+        for setting in SETTINGS {
+            if matches!(setting.kind, Kind::ReadOnly(_) | Kind::Enum(_)) {
+                continue;
+            }
+            if let Some(value) = setting.get(self) {
+                setting.validate(&value)?;
+            }
+        }
+
+        for (name, profile) in &self.profiles {
+            validate_profile_name(name)?;
+            let Profile { owner, repo, registry, proxy } = profile;
+            let fields = [
+                ("owner", owner),
+                ("repo", repo),
+                ("pull_registry", registry),
+                ("proxy", proxy),
+            ];
+            for (key, value) in fields {
+                if let Some(value) = value {
+                    find(key)?
+                        .validate(value)
+                        .map_err(|e| anyhow!("profile '{}': {}", name, e))?;
+                }
+            }
+        }
+
+        if let Some(profile) = &self.current_profile {
+            if !self.profiles.contains_key(profile) {
+                return Err(anyhow!("current_profile '{}' 不存在", profile));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 配置值的来源，优先级从低到高
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Profile(String),
+    Project(PathBuf),
+    Env(&'static str),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "默认值"),
+            Origin::System(path) => write!(f, "系统配置 {}", path.display()),
+            Origin::User(path) => write!(f, "用户配置 {}", path.display()),
+            Origin::Profile(name) => write!(f, "profile {}", name),
+            Origin::Project(path) => write!(f, "项目配置 {}", path.display()),
+            Origin::Env(name) => write!(f, "环境变量 {}", name),
+        }
+    }
+}
+
+/// 系统级配置，同一台机器上的所有用户共享
+fn system_config_path() -> PathBuf {
+    if cfg!(windows) {
+        let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(program_data).join("docker-sync").join("config")
+    } else {
+        PathBuf::from("/etc/docker-sync/config")
+    }
+}
+
+/// 从当前目录向上查找 .docker-sync.toml
+fn find_project_file() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// 系统或项目配置文件（TOML）中的配置项，images 只在项目配置中使用
+#[derive(Default)]
+struct Layer {
+    values: Vec<(&'static Setting, String)>,
+    images: Vec<String>,
+}
+
+/// 读取配置层；allowed 为 Some 时只接受其中的配置项，其余的给出警告并忽略
+fn read_layer(path: &Path, allowed: Option<&[&str]>) -> Result<Layer> {
+    let content = std::fs::read_to_string(path)?;
+    let table: toml::Table = content.parse()?;
+
+    let mut layer = Layer::default();
+    for (key, value) in table {
+        let text = match &value {
+            toml::Value::String(text) => text.clone(),
+            toml::Value::Integer(number) => number.to_string(),
+            toml::Value::Boolean(flag) => flag.to_string(),
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    toml::Value::String(text) => Ok(text.clone()),
+                    _ => Err(anyhow!("{} 的列表只能包含字符串", key)),
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join(","),
+            _ => return Err(anyhow!("{} 的值类型不受支持", key)),
+        };
+        if key == "images" {
+            layer.images = text.split(',').map(|image| image.trim().to_string()).collect();
+            continue;
+        }
+        match find(&key) {
+            Ok(setting) if allowed.is_some_and(|allowed| !allowed.contains(&setting.key)) => {
+                eprintln!("{} {}: 不支持在此设置 {}", "⚠️".yellow(), path.display(), setting.key)
+            }
+            Ok(setting) if !matches!(setting.kind, Kind::ReadOnly(_)) => layer.values.push((setting, text)),
+            Ok(setting) => eprintln!("{} {}: 不支持在此设置 {}", "⚠️".yellow(), path.display(), setting.key),
+            Err(e) => eprintln!("{} {}: {}", "⚠️".yellow(), path.display(), e),
+        }
+    }
+    Ok(layer)
+}
+
+impl Config {
+    /// 在用户配置（及生效的 profile）之上叠加系统配置、项目配置和 DOCKER_SYNC_* 环境变量，
+    /// 并记录每个值的来源；叠加的值不会被 save 写回用户配置
+    pub fn apply_layers(&mut self, user_path: &Path) {
+        let system_path = Some(system_config_path()).filter(|path| path.is_file());
+        self.apply_layer_files(user_path, system_path.as_deref(), find_project_file().as_deref());
+    }
+
+    fn apply_layer_files(&mut self, user_path: &Path, system_path: Option<&Path>, project_path: Option<&Path>) {
+        let default = Config::default();
+        let base = self.clone();
+        for setting in SETTINGS {
+            let origin = match &self.active_profile {
+                // 用户配置文件保存了所有字段，与默认值相同的视为未设置
+                _ if setting.get(self) == setting.get(&default) => Origin::Default,
+                Some(profile) if PROFILE_KEYS.contains(&setting.key) => Origin::Profile(profile.clone()),
+                _ => Origin::User(user_path.to_path_buf()),
+            };
+            self.origins.insert(setting.key, origin);
+        }
+
+        if let Some(system_path) = system_path {
+            match read_layer(system_path, None) {
+                Ok(layer) => {
+                    for (setting, value) in layer.values {
+                        // 系统配置只提供默认值，用户和 profile 的设置优先
+                        if self.origins.get(setting.key) == Some(&Origin::Default) {
+                            self.apply_layer_value(setting, &value, Origin::System(system_path.to_path_buf()));
+                        }
+                    }
+                }
+                Err(e) => eprintln!("{} 无法读取系统配置 {}: {}", "⚠️".yellow(), system_path.display(), e),
+            }
+        }
+
+        if let Some(project_path) = project_path {
+            match read_layer(project_path, Some(&PROJECT_KEYS)) {
+                Ok(layer) => {
+                    for (setting, value) in layer.values {
+                        self.apply_layer_value(setting, &value, Origin::Project(project_path.to_path_buf()));
+                    }
+                    self.project_images = layer.images;
+                }
+                Err(e) => eprintln!("{} 无法读取项目配置 {}: {}", "⚠️".yellow(), project_path.display(), e),
+            }
+        }
+
+        for setting in SETTINGS {
+            if matches!(setting.kind, Kind::ReadOnly(_)) {
+                continue;
+            }
+            if let Ok(value) = std::env::var(setting.env) {
+                if !value.trim().is_empty() {
+                    self.apply_layer_value(setting, &value, Origin::Env(setting.env));
+                }
+            }
+        }
+
+        self.base = Some(Box::new(base));
+    }
+
+    fn apply_layer_value(&mut self, setting: &'static Setting, value: &str, origin: Origin) {
+        match setting.set(self, value) {
+            Ok(()) => {
+                self.layered.insert(setting.key, setting.get(self));
+                self.origins.insert(setting.key, origin);
+            }
+            Err(e) => eprintln!("{} {}: {}", "⚠️".yellow(), origin, e),
+        }
+    }
+
+    /// 配置值的来源，未加载配置层时为 None
+    pub fn origin(&self, key: &str) -> Option<&Origin> {
+        self.origins.get(key)
+    }
+
+    /// 去掉系统、项目和环境变量叠加的值，得到应写入用户配置的内容；本次运行修改过的值保留
+    pub fn user_layer(&self) -> Config {
+        let mut config = self.clone();
+        if let Some(base) = &self.base {
+            for (key, applied) in &self.layered {
+                let setting = find(key).expect("layered keys come from SETTINGS");
+                if setting.get(self) == *applied {
+                    (setting.copy)(base, &mut config);
+                }
+            }
+        }
+        config
+    }
+}
+
+/// 列表配置项的显示形式，空列表视为未设置
+fn list(values: &[String]) -> Option<String> {
+    (!values.is_empty()).then(|| values.join(","))
+}
+
+/// 解析逗号分隔的列表配置项
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(|item| item.trim().to_string()).collect()
+}
+
+fn format_proxy_rules(rules: &BTreeMap<String, String>) -> Option<String> {
+    let rules: Vec<String> = rules.iter().map(|(host, proxy)| format!("{}={}", host, proxy)).collect();
+    (!rules.is_empty()).then(|| rules.join(","))
+}
+
+fn validate_host(value: &str) -> Result<()> {
+    if value.contains("://") {
+        return Err(anyhow!("只需填写主机名，例如 ghcr.io"));
+    }
+    let url = url::Url::parse(&format!("https://{}", value))?;
+    if url.host_str().is_none() || url.path() != "/" || url.query().is_some() || !url.username().is_empty() {
+        return Err(anyhow!("应为主机名，可带端口，例如 ghcr.io 或 registry.local:5000"));
+    }
+    Ok(())
+}
+
+fn validate_proxy(value: &str) -> Result<()> {
+    let url = url::Url::parse(value)?;
+    if !["http", "https", "socks5", "socks5h"].contains(&url.scheme()) {
+        return Err(anyhow!("仅支持 http://、https://、socks5://、socks5h:// 代理"));
+    }
+    if url.host_str().is_none() {
+        return Err(anyhow!("缺少代理主机"));
+    }
+    Ok(())
+}
+
+/// 解析 `config set proxy_rules` 的值：host=proxy,*.example.com=direct
+fn parse_proxy_rules(value: &str) -> Result<BTreeMap<String, String>> {
+    value
+        .split(',')
+        .map(|rule| {
+            let (host, proxy) = rule
+                .split_once('=')
+                .ok_or_else(|| anyhow!("规则 '{}' 应为 主机=代理地址 或 主机=direct", rule.trim()))?;
+            let (host, proxy) = (host.trim(), proxy.trim());
+            let domain = host.trim_start_matches("*.").trim_start_matches('.');
+            if host != "*" {
+                validate_host(domain)?;
+            }
+            if proxy != "direct" {
+                validate_proxy(proxy)?;
+            }
+            Ok((host.to_lowercase(), proxy.to_string()))
+        })
+        .collect()
+}
+
+fn validate_seconds(value: &str) -> Result<()> {
+    match value.parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Ok(()),
+        _ => Err(anyhow!("应为大于 0 的整数秒数")),
+    }
+}
+
+fn validate_file(value: &str) -> Result<()> {
+    if !Path::new(value).is_file() {
+        return Err(anyhow!("文件 {} 不存在", value));
+    }
+    Ok(())
+}
+
+fn validate_github_name(value: &str) -> Result<()> {
+    let valid = value.len() <= 39
+        && !value.starts_with('-')
+        && !value.ends_with('-')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        return Err(anyhow!("GitHub 用户名/组织名只能包含字母、数字和 -，且不超过 39 个字符"));
+    }
+    Ok(())
+}
+
+fn validate_repo_name(value: &str) -> Result<()> {
+    let valid = value.len() <= 100
+        && value != "."
+        && value != ".."
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(anyhow!("仓库名只能包含字母、数字、-、_ 和 ."));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用的临时配置文件，文件名带测试名和进程号以免并行测试互相覆盖
+    fn write_layer(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("docker-sync-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn keys(layer: &Layer) -> Vec<(&str, &str)> {
+        layer.values.iter().map(|(setting, value)| (setting.key, value.as_str())).collect()
+    }
+
+    #[test]
+    fn read_layer_parses_values_and_images() {
+        let path = write_layer(
+            "read-values",
+            r#"
+owner = "my-team"
+connect_timeout = 30
+helper_registries = ["ghcr.nju.edu.cn", "registry.local:5000"]
+images = ["nginx:alpine", " redis:7"]
+"#,
+        );
+        let layer = read_layer(&path, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let values = keys(&layer);
+        assert!(values.contains(&("owner", "my-team")));
+        assert!(values.contains(&("connect_timeout", "30")));
+        assert!(values.contains(&("helper_registries", "ghcr.nju.edu.cn,registry.local:5000")));
+        assert_eq!(layer.images, ["nginx:alpine", "redis:7"]);
+    }
+
+    #[test]
+    fn read_layer_ignores_unknown_and_read_only_keys() {
+        let path = write_layer("read-unknown", "no_such_key = \"x\"\ncurrent_profile = \"work\"\nrepo = \"sync\"\n");
+        let layer = read_layer(&path, None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(keys(&layer), [("repo", "sync")]);
+    }
+
+    #[test]
+    fn read_layer_rejects_unsupported_values() {
+        let path = write_layer("read-invalid", "ca_bundles = [1, 2]\n");
+        let result = read_layer(&path, None);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_layer_only_accepts_allowed_keys() {
+        let path = write_layer(
+            "read-allowed",
+            r#"
+owner = "my-team"
+proxy = "http://attacker.example.com:8080"
+ghcr_registry = "attacker.example.com"
+ca_bundles = ["/tmp/attacker.pem"]
+images = ["nginx:alpine"]
+"#,
+        );
+        let layer = read_layer(&path, Some(&PROJECT_KEYS)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(keys(&layer), [("owner", "my-team")]);
+        assert_eq!(layer.images, ["nginx:alpine"]);
+    }
+
+    #[test]
+    fn every_setting_reads_writes_copies_and_unsets_its_own_field() {
+        let values = [
+            ("pull_registry", "mirror.example.com"),
+            ("ghcr_registry", "registry.local:5000"),
+            ("custom_registries", "a.example.com,b.example.com"),
+            ("helper_registries", "ghcr.nju.edu.cn"),
+            ("proxy", "http://127.0.0.1:7890"),
+            ("proxy_rules", "*.corp.example=direct,ghcr.io=http://127.0.0.1:7890"),
+            ("connect_timeout", "30"),
+            ("request_timeout", "600"),
+            ("ca_bundles", "/etc/hosts"),
+            ("owner", "my-team"),
+            ("repo", "sync"),
+            ("runs_on", "self-hosted,linux"),
+            ("oauth_client_id", "Iv1.abc"),
+            ("credential_store", "encrypted"),
+        ];
+        let default = Config::default();
+        for setting in SETTINGS {
+            if let Kind::ReadOnly(_) = setting.kind {
+                continue;
+            }
+            let (_, value) = values
+                .iter()
+                .find(|(key, _)| *key == setting.key)
+                .unwrap_or_else(|| panic!("no test value for {}", setting.key));
+
+            let mut config = Config::default();
+            setting.set(&mut config, value).unwrap();
+            assert_eq!(setting.get(&config).as_deref(), Some(*value), "{}", setting.key);
+            // 只修改自己的字段
+            for other in SETTINGS.iter().filter(|other| other.key != setting.key) {
+                assert_eq!(other.get(&config), other.get(&default), "{} changed {}", setting.key, other.key);
+            }
+
+            let mut copy = Config::default();
+            (setting.copy)(&config, &mut copy);
+            assert_eq!(setting.get(&copy).as_deref(), Some(*value), "{}", setting.key);
+
+            setting.unset(&mut config).unwrap();
+            assert_eq!(setting.get(&config), setting.get(&default), "{}", setting.key);
+        }
+    }
+
+    #[test]
+    fn read_only_settings_cannot_be_changed() {
+        let setting = find("current_profile").unwrap();
+        let mut config = Config::default();
+        assert!(setting.set(&mut config, "work").is_err());
+        assert!(setting.unset(&mut config).is_err());
+    }
+
+    #[test]
+    fn parse_proxy_rules_accepts_proxies_and_direct() {
+        let rules = parse_proxy_rules("GHCR.io=http://127.0.0.1:7890, *.corp.example=direct,*=socks5h://127.0.0.1:1080").unwrap();
+        assert_eq!(rules.get("ghcr.io").map(String::as_str), Some("http://127.0.0.1:7890"));
+        assert_eq!(rules.get("*.corp.example").map(String::as_str), Some("direct"));
+        assert_eq!(rules.get("*").map(String::as_str), Some("socks5h://127.0.0.1:1080"));
+    }
+
+    #[test]
+    fn parse_proxy_rules_rejects_invalid_rules() {
+        assert!(parse_proxy_rules("ghcr.io").is_err());
+        assert!(parse_proxy_rules("https://ghcr.io=direct").is_err());
+        assert!(parse_proxy_rules("ghcr.io=ftp://127.0.0.1:21").is_err());
+        assert!(parse_proxy_rules("ghcr.io=not a url").is_err());
+    }
+
+    #[test]
+    fn system_layer_only_fills_defaults() {
+        let user = Path::new("config.json");
+        let system = write_layer("system-defaults", "owner = \"system-team\"\nrepo = \"system-repo\"\n");
+        let mut config = Config::default();
+        config.owner = Some("user-team".to_string());
+        config.apply_layer_files(user, Some(&system), None);
+        std::fs::remove_file(&system).unwrap();
+
+        assert_eq!(config.owner.as_deref(), Some("user-team"));
+        assert_eq!(config.origin("owner"), Some(&Origin::User(user.to_path_buf())));
+        assert_eq!(config.repo.as_deref(), Some("system-repo"));
+        assert_eq!(config.origin("repo"), Some(&Origin::System(system.clone())));
+    }
+
+    #[test]
+    fn project_layer_overrides_user_and_system() {
+        let user = Path::new("config.json");
+        let system = write_layer("project-system", "repo = \"system-repo\"\n");
+        let project = write_layer(
+            "project-overrides",
+            "owner = \"project-team\"\nrepo = \"project-repo\"\nproxy = \"http://attacker.example.com:8080\"\n",
+        );
+        let mut config = Config::default();
+        config.owner = Some("user-team".to_string());
+        config.apply_layer_files(user, Some(&system), Some(&project));
+        std::fs::remove_file(&system).unwrap();
+        std::fs::remove_file(&project).unwrap();
+
+        assert_eq!(config.owner.as_deref(), Some("project-team"));
+        assert_eq!(config.repo.as_deref(), Some("project-repo"));
+        assert_eq!(config.origin("repo"), Some(&Origin::Project(project.clone())));
+        assert_eq!(config.proxy, None);
+        // 叠加的值不写回用户配置
+        assert_eq!(config.user_layer().owner.as_deref(), Some("user-team"));
+    }
+
+    #[test]
+    fn env_overrides_project_layer() {
+        let user = Path::new("config.json");
+        let project = write_layer("env-project", "runs_on = \"project-runner\"\n");
+        // 只有这个测试设置 DOCKER_SYNC_RUNS_ON，不影响并行的其他测试
+        std::env::set_var("DOCKER_SYNC_RUNS_ON", "env-runner");
+        let mut config = Config::default();
+        config.apply_layer_files(user, None, Some(&project));
+        std::env::remove_var("DOCKER_SYNC_RUNS_ON");
+        std::fs::remove_file(&project).unwrap();
+
+        assert_eq!(config.runs_on, ["env-runner"]);
+        assert_eq!(config.origin("runs_on"), Some(&Origin::Env("DOCKER_SYNC_RUNS_ON")));
+    }
+}